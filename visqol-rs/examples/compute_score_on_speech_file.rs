@@ -1,20 +1,22 @@
 use std::error::Error;
 
 use visqol_rs::{
-    constants::{DEFAULT_WINDOW_SIZE, NUM_BANDS_SPEECH},
-    variant::Variant,
+    constants::DEFAULT_WINDOW_SIZE,
+    variant::{Variant, WidebandMapping},
     *,
 };
 fn main() -> Result<(), Box<dyn Error>> {
     let path_to_reference_file = "./test_data/clean_speech/reference_signal.wav";
     let path_to_degraded_file = "./test_data/clean_speech/degraded_signal.wav";
 
-    let mut visqol = visqol_manager::VisqolManager::<NUM_BANDS_SPEECH>::new(
+    let mut visqol = visqol_manager::VisqolManager::new(
         Variant::Wideband {
-            use_unscaled_mos_mapping: false,
+            mapping: WidebandMapping::Polynomial {
+                use_unscaled_mos_mapping: false,
+            },
         },
         DEFAULT_WINDOW_SIZE,
-    );
+    )?;
 
     let similarity_result = visqol.run(path_to_reference_file, path_to_degraded_file)?;
 
@@ -0,0 +1,160 @@
+use crate::{
+    bootstrap::{self, SplitMix64},
+    patch_similarity_comparator::PatchSimilarityResult,
+    similarity_result::SimilarityResult,
+    similarity_to_quality_mapper::SimilarityToQualityMapper,
+    visqol,
+    visqol_error::VisqolError,
+};
+use serde::Serialize;
+
+/// Scores of 2 degraded signals against the same reference and the significance of their difference
+#[derive(Debug, Serialize)]
+pub struct AbComparisonResult {
+    /// Similarity of degraded signal A to the reference
+    pub result_a: SimilarityResult,
+    /// Similarity of degraded signal B to the reference
+    pub result_b: SimilarityResult,
+    /// MOS of A minus MOS of B, positive if A is better
+    pub moslqo_difference: f64,
+    /// Lower bound of the confidence interval of `moslqo_difference`
+    pub lower: f64,
+    /// Upper bound of the confidence interval of `moslqo_difference`
+    pub upper: f64,
+    /// Probability that the confidence interval contains the difference, e.g. 0.95
+    pub confidence_level: f64,
+    /// Two-sided p-value of the hypothesis that A and B have the same MOS, at least `1 / (iterations + 1)`
+    pub p_value: f64,
+    /// Number of resampled patch sets
+    pub iterations: usize,
+}
+
+/// Result of the paired bootstrap test of `paired_bootstrap`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PairedBootstrap {
+    /// Lower bound of the confidence interval of the MOS difference
+    pub lower: f64,
+    /// Upper bound of the confidence interval of the MOS difference
+    pub upper: f64,
+    /// Two-sided p-value of the hypothesis that the MOS difference is 0, at least `1 / (iterations + 1)`
+    pub p_value: f64,
+}
+
+/// Tests whether the patches `patches_a` and `patches_b`, compared to the same reference patches, map to a different MOS.
+/// Draws `iterations` sets of reference patches with replacement and maps the matching patches of A and B to a MOS with `mapper`.
+/// The interval spans the central `confidence_level` of the resampled MOS differences. The p-value is `(k + 1) / (iterations + 1)`,
+/// where `k` is twice the number of differences on the less frequent side of 0, so that a finite number of iterations never yields 0.
+/// Patch `i` of both lists must be compared to the same reference patch, see `VisqolManager::score_ab_signals`.
+/// Returns `None` if there are no patches or iterations, and an error if the lists differ in length or `mapper` fails.
+pub fn paired_bootstrap(
+    patches_a: &[PatchSimilarityResult],
+    patches_b: &[PatchSimilarityResult],
+    frame_duration: f64,
+    mapper: &dyn SimilarityToQualityMapper,
+    iterations: usize,
+    confidence_level: f64,
+    seed: u64,
+) -> Result<Option<PairedBootstrap>, VisqolError> {
+    if patches_a.len() != patches_b.len() {
+        return Err(VisqolError::DifferentPatchSets {
+            patch_index: patches_a.len().min(patches_b.len()),
+        });
+    }
+    if patches_a.is_empty() || iterations == 0 {
+        return Ok(None);
+    }
+
+    let num_patches = patches_a.len();
+    let mut rng = SplitMix64::new(seed);
+    let mut resampled_a = Vec::<PatchSimilarityResult>::with_capacity(num_patches);
+    let mut resampled_b = Vec::<PatchSimilarityResult>::with_capacity(num_patches);
+    let mut differences = (0..iterations)
+        .map(|_| {
+            resampled_a.clear();
+            resampled_b.clear();
+            for _ in 0..num_patches {
+                let index = rng.next_index(num_patches);
+                resampled_a.push(patches_a[index].clone());
+                resampled_b.push(patches_b[index].clone());
+            }
+            Ok(
+                visqol::predict_quality(&resampled_a, frame_duration, mapper)?
+                    - visqol::predict_quality(&resampled_b, frame_duration, mapper)?,
+            )
+        })
+        .collect::<Result<Vec<f64>, VisqolError>>()?;
+    differences.sort_by(|a, b| a.total_cmp(b));
+
+    let num_not_above = differences
+        .iter()
+        .filter(|&&difference| difference <= 0.0)
+        .count();
+    let num_not_below = differences
+        .iter()
+        .filter(|&&difference| difference >= 0.0)
+        .count();
+    let num_extreme = 2 * num_not_above.min(num_not_below);
+    let tail = (1.0 - confidence_level) / 2.0;
+    Ok(Some(PairedBootstrap {
+        lower: bootstrap::percentile(&differences, tail),
+        upper: bootstrap::percentile(&differences, 1.0 - tail),
+        p_value: ((num_extreme + 1) as f64 / (iterations + 1) as f64).min(1.0),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utility::{patch, LinearMapper};
+
+    fn patches(similarities: &[f64]) -> Vec<PatchSimilarityResult> {
+        similarities
+            .iter()
+            .map(|&similarity| patch(0.0, 0.0, vec![similarity; 2]))
+            .collect()
+    }
+
+    #[test]
+    fn consistently_better_patches_are_significant() {
+        let similarities = [0.9, 0.5, 0.8, 0.7, 0.95, 0.6, 0.85, 0.75];
+        let patches_b = patches(&similarities);
+        let patches_a = patches(&similarities.map(|similarity| similarity + 0.02));
+        let patches_noise = patches(&[0.88, 0.55, 0.76, 0.72, 0.93, 0.62, 0.83, 0.78]);
+
+        let better = paired_bootstrap(&patches_a, &patches_b, 0.02, &LinearMapper, 1000, 0.95, 7)
+            .unwrap()
+            .unwrap();
+        let noise = paired_bootstrap(
+            &patches_noise,
+            &patches_b,
+            0.02,
+            &LinearMapper,
+            1000,
+            0.95,
+            7,
+        )
+        .unwrap()
+        .unwrap();
+
+        // A is better in every resampled set, which bounds the p-value by 1 / (iterations + 1).
+        assert_eq!(better.p_value, 1.0 / 1001.0);
+        assert!(better.lower > 0.0);
+        assert!(noise.p_value > 0.05);
+        assert!(noise.lower < 0.0 && noise.upper > 0.0);
+    }
+
+    #[test]
+    fn degenerate_inputs_are_rejected() {
+        let patches_a = patches(&[0.9, 0.8, 0.7]);
+        let test = |a: &[PatchSimilarityResult], b: &[PatchSimilarityResult], iterations| {
+            paired_bootstrap(a, b, 0.02, &LinearMapper, iterations, 0.95, 7)
+        };
+
+        assert!(test(&patches_a, &patches_a, 0).unwrap().is_none());
+        assert!(test(&[], &[], 1000).unwrap().is_none());
+        assert!(matches!(
+            test(&patches_a, &patches_a[..2], 1000),
+            Err(VisqolError::DifferentPatchSets { patch_index: 2 })
+        ));
+    }
+}
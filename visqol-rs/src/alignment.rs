@@ -10,7 +10,7 @@ pub fn align_and_truncate(
     ref_signal: &AudioSignal,
     deg_signal: &AudioSignal,
 ) -> Option<(AudioSignal, AudioSignal, f64)> {
-    let (aligned_deg_signal, lag) = globally_align(ref_signal, deg_signal)?;
+    let (aligned_deg_signal, lag, _) = globally_align(ref_signal, deg_signal)?;
 
     let mut new_ref_matrix = ref_signal.data_matrix.clone();
     let mut new_deg_matrix = aligned_deg_signal.data_matrix;
@@ -44,10 +44,12 @@ pub fn align_and_truncate(
 
 /// Aligns a degraded signal to the reference signal, truncating them to
 /// be the same length.
+/// Returns the aligned degraded signal, the lag in seconds by which it was shifted and the correlation of the signal envelopes at that lag.
+/// The correlation is `None` if an envelope is constant.
 pub fn globally_align(
     ref_signal: &AudioSignal,
     deg_signal: &AudioSignal,
-) -> Option<(AudioSignal, f64)> {
+) -> Option<(AudioSignal, f64, Option<f64>)> {
     let ref_upper_env = envelope::calculate_upper_env(&ref_signal.data_matrix)?;
     let deg_upper_env = envelope::calculate_upper_env(&deg_signal.data_matrix)?;
 
@@ -57,8 +59,13 @@ pub fn globally_align(
         // If signals are correlated already, return deg signal and 0.
         let new_deg_signal =
             AudioSignal::new(deg_signal.data_matrix.as_slice()?, deg_signal.sample_rate);
+        let correlation = xcorr::calculate_correlation_at_lag(
+            ref_upper_env.as_slice()?,
+            deg_upper_env.as_slice()?,
+            0,
+        );
 
-        Some((new_deg_signal, 0.0f64))
+        Some((new_deg_signal, 0.0f64, correlation))
     } else {
         let mut new_deg_matrix = deg_signal.data_matrix.clone();
         // align degraded matrix
@@ -80,9 +87,15 @@ pub fn globally_align(
                 .expect("Failed to create AudioSignal from slice!"),
             deg_signal.sample_rate,
         );
+        let correlation = xcorr::calculate_correlation_at_lag(
+            ref_upper_env.as_slice()?,
+            deg_upper_env.as_slice()?,
+            best_lag,
+        );
         Some((
             new_deg_signal,
             (best_lag as f64 / deg_signal.sample_rate as f64),
+            correlation,
         ))
     }
 }
@@ -0,0 +1,425 @@
+#[cfg(any(
+    feature = "flac",
+    feature = "vorbis",
+    feature = "opus",
+    feature = "mp3",
+    feature = "aiff"
+))]
+use crate::compressed_audio_decoder;
+use crate::{math_utils, visqol_error::VisqolError, wav_reader::WavFile};
+use std::{error::Error, fmt, fs::File, io::Read, str::FromStr};
+
+/// The audio file formats which can be detected from a file header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioFormat {
+    Wav,
+    Flac,
+    Vorbis,
+    Opus,
+    Mp3,
+    Aiff,
+}
+
+impl AudioFormat {
+    /// Number of bytes at the beginning of a file which are inspected by `detect`.
+    pub const HEADER_SIZE: usize = 64;
+
+    /// Detects the format of an audio file from its first bytes. Returns `None` if the format is unknown.
+    pub fn detect(header: &[u8]) -> Option<Self> {
+        if header.len() >= 12 && &header[0..4] == b"RIFF" && &header[8..12] == b"WAVE" {
+            return Some(AudioFormat::Wav);
+        }
+        if header.starts_with(b"fLaC") {
+            return Some(AudioFormat::Flac);
+        }
+        if header.len() >= 12
+            && &header[0..4] == b"FORM"
+            && (&header[8..12] == b"AIFF" || &header[8..12] == b"AIFC")
+        {
+            return Some(AudioFormat::Aiff);
+        }
+        if header.starts_with(b"OggS") {
+            return Self::detect_ogg_codec(header);
+        }
+        // Either an ID3v2 tag or the frame sync of an MPEG layer III frame.
+        if header.starts_with(b"ID3")
+            || (header.len() >= 2 && header[0] == 0xFF && header[1] & 0xE6 == 0xE2)
+        {
+            return Some(AudioFormat::Mp3);
+        }
+        None
+    }
+
+    /// Returns the name of the cargo feature which enables decoding this format, or `None` if it is always available.
+    pub fn feature(&self) -> Option<&'static str> {
+        match self {
+            AudioFormat::Wav => None,
+            AudioFormat::Flac => Some("flac"),
+            AudioFormat::Vorbis => Some("vorbis"),
+            AudioFormat::Opus => Some("opus"),
+            AudioFormat::Mp3 => Some("mp3"),
+            AudioFormat::Aiff => Some("aiff"),
+        }
+    }
+
+    /// Returns `true` if this library was built with support for decoding this format.
+    pub fn is_enabled(&self) -> bool {
+        match self {
+            AudioFormat::Wav => true,
+            AudioFormat::Flac => cfg!(feature = "flac"),
+            AudioFormat::Vorbis => cfg!(feature = "vorbis"),
+            AudioFormat::Opus => cfg!(feature = "opus"),
+            AudioFormat::Mp3 => cfg!(feature = "mp3"),
+            AudioFormat::Aiff => cfg!(feature = "aiff"),
+        }
+    }
+
+    /// Inspects the first packet of the first Ogg page to tell the contained codec.
+    fn detect_ogg_codec(header: &[u8]) -> Option<Self> {
+        const PAGE_HEADER_SIZE: usize = 27;
+        let num_segments = *header.get(PAGE_HEADER_SIZE - 1)? as usize;
+        let first_packet = header.get(PAGE_HEADER_SIZE + num_segments..)?;
+
+        if first_packet.starts_with(b"OpusHead") {
+            Some(AudioFormat::Opus)
+        } else if first_packet.starts_with(b"\x01vorbis") {
+            Some(AudioFormat::Vorbis)
+        } else {
+            None
+        }
+    }
+}
+
+/// The sample formats supported for headerless raw PCM data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawSampleFormat {
+    /// Signed 16 bit integer, little endian
+    S16Le,
+    /// 32 bit float, little endian
+    F32Le,
+}
+
+impl RawSampleFormat {
+    /// Returns the number of bytes of a single sample.
+    pub fn sample_size(&self) -> usize {
+        match self {
+            RawSampleFormat::S16Le => 2,
+            RawSampleFormat::F32Le => 4,
+        }
+    }
+}
+
+/// Describes the layout of headerless raw PCM data.
+/// Can be parsed from a string of the form `<sample format>:<sample rate>[:<channels>]`, e.g. `s16le:48000:2`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawFormat {
+    pub sample_format: RawSampleFormat,
+    pub sample_rate: u32,
+    pub num_channels: u16,
+}
+
+impl FromStr for RawFormat {
+    type Err = VisqolError;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let invalid = || VisqolError::InvalidRawFormat {
+            spec: spec.to_string(),
+        };
+        let mut parts = spec.split(':');
+
+        let sample_format = match parts.next().map(str::to_ascii_lowercase).as_deref() {
+            Some("s16le") => RawSampleFormat::S16Le,
+            Some("f32le") => RawSampleFormat::F32Le,
+            _ => return Err(invalid()),
+        };
+        let sample_rate = parts
+            .next()
+            .and_then(|rate| rate.parse::<u32>().ok())
+            .filter(|&rate| rate > 0)
+            .ok_or_else(invalid)?;
+        let num_channels = match parts.next() {
+            Some(channels) => channels
+                .parse::<u16>()
+                .ok()
+                .filter(|&channels| channels > 0)
+                .ok_or_else(invalid)?,
+            None => 1,
+        };
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            sample_format,
+            sample_rate,
+            num_channels,
+        })
+    }
+}
+
+impl fmt::Display for RawFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sample_format = match self.sample_format {
+            RawSampleFormat::S16Le => "s16le",
+            RawSampleFormat::F32Le => "f32le",
+        };
+        write!(
+            f,
+            "{}:{}:{}",
+            sample_format, self.sample_rate, self.num_channels
+        )
+    }
+}
+
+/// Represents the metadata and decoded contents of an audio file in any of the supported formats.
+pub struct AudioSource {
+    /// The number of channels in the audio file
+    pub num_channels: u16,
+    /// The sample rate of the audio file
+    pub sample_rate: u32,
+    /// The interleaved samples in the audio file, scaled from -1.0 to 1.0.
+    pub samples: Vec<f64>,
+}
+
+impl AudioSource {
+    /// Given a `file_path` to an audio file, its format is detected from the file header and the contents are decoded.
+    /// Formats other than wav are only available if the corresponding cargo feature is enabled.
+    pub fn open(file_path: &str) -> Result<Self, Box<dyn Error>> {
+        let mut header = Vec::with_capacity(AudioFormat::HEADER_SIZE);
+        File::open(file_path)?
+            .take(AudioFormat::HEADER_SIZE as u64)
+            .read_to_end(&mut header)?;
+
+        let format =
+            AudioFormat::detect(&header).ok_or_else(|| VisqolError::UnknownAudioFormat {
+                file_path: file_path.to_string(),
+            })?;
+
+        match format {
+            AudioFormat::Wav => {
+                let wav_file = WavFile::open(file_path)?;
+                Ok(Self {
+                    num_channels: wav_file.num_channels,
+                    sample_rate: wav_file.sample_rate,
+                    samples: wav_file.samples,
+                })
+            }
+            #[cfg(any(
+                feature = "flac",
+                feature = "vorbis",
+                feature = "opus",
+                feature = "mp3",
+                feature = "aiff"
+            ))]
+            _ if format.is_enabled() => compressed_audio_decoder::decode(file_path),
+            _ => Err(Box::new(VisqolError::AudioFormatNotEnabled {
+                format,
+                feature: format.feature().unwrap_or_default(),
+            })),
+        }
+    }
+
+    /// Given a `file_path` to a file with headerless raw PCM data, its contents are read according to `format`.
+    pub fn open_raw(file_path: &str, format: &RawFormat) -> Result<Self, Box<dyn Error>> {
+        let bytes = std::fs::read(file_path)?;
+        let sample_size = format.sample_format.sample_size();
+        let num_channels = format.num_channels as usize;
+
+        if !bytes.len().is_multiple_of(sample_size * num_channels) {
+            return Err(Box::new(VisqolError::InvalidInterleavedLength {
+                num_samples: bytes.len() / sample_size,
+                num_channels,
+            }));
+        }
+
+        let samples = match format.sample_format {
+            RawSampleFormat::S16Le => {
+                let samples = bytes
+                    .chunks_exact(sample_size)
+                    .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]) as i32)
+                    .collect::<Vec<_>>();
+                math_utils::normalize_int_to_double(&samples, 16)
+            }
+            RawSampleFormat::F32Le => bytes
+                .chunks_exact(sample_size)
+                .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64)
+                .collect(),
+        };
+
+        Ok(Self {
+            num_channels: format.num_channels,
+            sample_rate: format.sample_rate,
+            samples,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_utils;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn formats_are_detected_from_header() {
+        let wav = b"RIFF\x24\x00\x00\x00WAVEfmt ";
+        let aiff = b"FORM\x00\x00\x00\x00AIFFCOMM";
+        let mut ogg_vorbis = b"OggS".to_vec();
+        ogg_vorbis.extend_from_slice(&[0; 22]);
+        ogg_vorbis.extend_from_slice(&[1, 30]);
+        ogg_vorbis.extend_from_slice(b"\x01vorbis");
+
+        assert_eq!(AudioFormat::detect(wav), Some(AudioFormat::Wav));
+        assert_eq!(AudioFormat::detect(b"fLaC\x00"), Some(AudioFormat::Flac));
+        assert_eq!(AudioFormat::detect(aiff), Some(AudioFormat::Aiff));
+        assert_eq!(AudioFormat::detect(&ogg_vorbis), Some(AudioFormat::Vorbis));
+        assert_eq!(AudioFormat::detect(b"ID3\x04\x00"), Some(AudioFormat::Mp3));
+        assert_eq!(
+            AudioFormat::detect(&[0xFF, 0xFB, 0x90, 0x64]),
+            Some(AudioFormat::Mp3)
+        );
+        assert_eq!(AudioFormat::detect(b"\x00\x01\x02\x03"), None);
+
+        let mut opus_header = Vec::new();
+        File::open("test_data/compressed/CA01_01_1s.opus")
+            .unwrap()
+            .take(AudioFormat::HEADER_SIZE as u64)
+            .read_to_end(&mut opus_header)
+            .unwrap();
+        assert_eq!(AudioFormat::detect(&opus_header), Some(AudioFormat::Opus));
+    }
+
+    /// Decodes `file_path` and compares it to the first second of the wav file it was encoded from.
+    #[allow(unused)]
+    fn assert_matches_wav(file_path: &str, tolerance: f64) {
+        let expected = audio_utils::load_as_mono("test_data/clean_speech/CA01_01.wav").unwrap();
+        let source = AudioSource::open(file_path).unwrap();
+
+        assert_eq!(source.num_channels, 1);
+        assert_eq!(source.sample_rate, expected.sample_rate);
+        assert_eq!(source.samples.len(), 48000);
+        for (a, b) in source.samples.iter().zip(expected.data_matrix.iter()) {
+            assert_abs_diff_eq!(a, b, epsilon = tolerance);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "flac")]
+    fn flac_file_is_decoded() {
+        assert_matches_wav("test_data/compressed/CA01_01_1s.flac", 0.0001);
+    }
+
+    #[test]
+    #[cfg(feature = "aiff")]
+    fn aiff_file_is_decoded() {
+        assert_matches_wav("test_data/compressed/CA01_01_1s.aiff", 0.0001);
+    }
+
+    #[test]
+    #[cfg(feature = "mp3")]
+    fn mp3_file_is_decoded() {
+        // The encoder delay and padding are removed using the LAME tag.
+        assert_matches_wav("test_data/compressed/CA01_01_1s.mp3", 0.001);
+    }
+
+    #[test]
+    #[cfg(feature = "vorbis")]
+    fn vorbis_file_is_decoded() {
+        // The end of the stream is trimmed using the granule position of the last page.
+        assert_matches_wav("test_data/compressed/CA01_01_1s.ogg", 0.005);
+    }
+
+    #[test]
+    #[cfg(feature = "opus")]
+    fn opus_file_is_decoded() {
+        let expected = audio_utils::load_as_mono("test_data/clean_speech/CA01_01.wav").unwrap();
+        let source = AudioSource::open("test_data/compressed/CA01_01_1s.opus").unwrap();
+
+        assert_eq!(source.num_channels, 1);
+        assert_eq!(source.sample_rate, 48000);
+        // Opus is lossy, so only check that the decoded signal follows the original closely.
+        let error_energy: f64 = source
+            .samples
+            .iter()
+            .zip(expected.data_matrix.iter())
+            .map(|(a, b)| (a - b).powi(2))
+            .sum();
+        let signal_energy: f64 = expected.data_matrix.iter().take(48000).map(|e| e * e).sum();
+        assert!(error_energy < 0.1 * signal_energy);
+    }
+
+    #[test]
+    fn raw_formats_are_parsed() {
+        assert_eq!(
+            "s16le:48000:2".parse::<RawFormat>().unwrap(),
+            RawFormat {
+                sample_format: RawSampleFormat::S16Le,
+                sample_rate: 48000,
+                num_channels: 2,
+            }
+        );
+        assert_eq!(
+            "F32LE:16000".parse::<RawFormat>().unwrap().to_string(),
+            "f32le:16000:1"
+        );
+        for invalid in [
+            "s24le:48000",
+            "s16le",
+            "s16le:0",
+            "s16le:48000:0",
+            "f32le:8000:1:1",
+        ] {
+            assert!(invalid.parse::<RawFormat>().is_err());
+        }
+    }
+
+    #[test]
+    fn raw_pcm_matches_wav() {
+        let wav_file = WavFile::open("test_data/clean_speech/CA01_01.wav").unwrap();
+        let raw_s16 = wav_file
+            .samples
+            .iter()
+            .flat_map(|&sample| ((sample * 32767.0).round() as i16).to_le_bytes())
+            .collect::<Vec<u8>>();
+        let raw_f32 = wav_file
+            .samples
+            .iter()
+            .flat_map(|&sample| (sample as f32).to_le_bytes())
+            .collect::<Vec<u8>>();
+
+        for (bytes, sample_format) in [
+            (raw_s16, RawSampleFormat::S16Le),
+            (raw_f32, RawSampleFormat::F32Le),
+        ] {
+            let path =
+                std::env::temp_dir().join(format!("visqol_rs_CA01_01_{:?}.raw", sample_format));
+            std::fs::write(&path, bytes).unwrap();
+            let format = RawFormat {
+                sample_format,
+                sample_rate: 48000,
+                num_channels: 1,
+            };
+            let source = AudioSource::open_raw(path.to_str().unwrap(), &format).unwrap();
+
+            assert_eq!(source.sample_rate, 48000);
+            assert_eq!(source.samples.len(), wav_file.samples.len());
+            for (a, b) in source.samples.iter().zip(wav_file.samples.iter()) {
+                assert_abs_diff_eq!(a, b, epsilon = 0.00001);
+            }
+        }
+    }
+
+    #[test]
+    fn raw_pcm_must_fill_all_channels() {
+        let path = std::env::temp_dir().join("visqol_rs_three_samples.raw");
+        std::fs::write(&path, [0u8; 6]).unwrap();
+        let format = "s16le:48000:2".parse::<RawFormat>().unwrap();
+        assert!(AudioSource::open_raw(path.to_str().unwrap(), &format).is_err());
+    }
+
+    #[test]
+    #[cfg(not(feature = "flac"))]
+    fn disabled_format_is_reported() {
+        assert!(AudioSource::open("test_data/compressed/CA01_01_1s.flac").is_err());
+    }
+}
@@ -1,18 +1,37 @@
 use std::error::Error;
 
 use crate::audio_signal::AudioSignal;
-use crate::math_utils;
+use crate::audio_source::{AudioSource, RawFormat};
 use crate::spectrogram::Spectrogram;
-use crate::wav_reader::WavFile;
 use ndarray::{Array1, Array2, Axis, ShapeBuilder};
 use num::complex::Complex64;
 use num_traits::Zero;
+use serde::{Deserialize, Serialize};
 
 // Constants
 const SPL_REFERENCE_POINT: f64 = 0.00002;
 const NOISE_FLOOR_RELATIVE_TO_PEAK_DB: f64 = 45.0;
 const NOISE_FLOOR_ABSOLUTE_DB: f64 = -45.0;
 
+/// Noise floors that are applied to the dB spectrograms of the reference and degraded signals before they are compared.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NoiseFloor {
+    /// Lowest level of any spectrogram bin in dB
+    pub absolute_db: f64,
+    /// Distance in dB below the loudest bin of a frame, below which bins of both spectrograms are raised
+    pub relative_to_peak_db: f64,
+}
+
+impl Default for NoiseFloor {
+    fn default() -> Self {
+        Self {
+            absolute_db: NOISE_FLOOR_ABSOLUTE_DB,
+            relative_to_peak_db: NOISE_FLOOR_RELATIVE_TO_PEAK_DB,
+        }
+    }
+}
+
 /// Returns a copy of `degraded` which has the same SPL as `reference`.
 pub fn scale_to_match_sound_pressure_level(
     reference: &AudioSignal,
@@ -32,7 +51,7 @@ pub fn scale_to_match_sound_pressure_level(
 }
 
 /// Computes the sound pressure level of an audio signal in dB
-fn calculate_sound_pressure_level(signal: &AudioSignal) -> f64 {
+pub fn calculate_sound_pressure_level(signal: &AudioSignal) -> f64 {
     let energy: f64 = signal
         .data_matrix
         .iter()
@@ -45,21 +64,65 @@ fn calculate_sound_pressure_level(signal: &AudioSignal) -> f64 {
 /// Calculates the per-column sum of a 2d array and returns them as a 1d array
 fn to_mono_matrix(sample_matrix: &Array2<f64>) -> Array1<f64> { sample_matrix.sum_axis(Axis(1)) }
 
-/// Given a `file_path` to a wav file on disk, this file is loaded. If there are multiple channels, these are summed and normalized to 1 mono channel.
+/// Given a `file_path` to an audio file on disk, this file is loaded. If there are multiple channels, these are summed and normalized to 1 mono channel.
+/// The decoder is chosen from the file header, see `AudioSource` for the supported formats.
 pub fn load_as_mono(file_path: &str) -> Result<AudioSignal, Box<dyn Error>> {
-    let wav_reader = WavFile::open(file_path)?;
+    let source = AudioSource::open(file_path)?;
 
-    let data_vector_float = math_utils::normalize_int16_to_double(&wav_reader.samples);
-    let final_signal = extract_multichannel(wav_reader.num_channels as usize, &data_vector_float);
+    Ok(AudioSignal {
+        data_matrix: downmix_interleaved(&source.samples, source.num_channels as usize),
+        sample_rate: source.sample_rate,
+    })
+}
 
-    let final_signal = to_mono_matrix(&final_signal);
+/// Given a `file_path` to a file with headerless raw PCM data laid out as described by `format`, this file is loaded.
+/// If there are multiple channels, these are summed and normalized to 1 mono channel.
+pub fn load_raw_as_mono(
+    file_path: &str,
+    format: &RawFormat,
+) -> Result<AudioSignal, Box<dyn Error>> {
+    let source = AudioSource::open_raw(file_path, format)?;
 
     Ok(AudioSignal {
-        data_matrix: final_signal / wav_reader.num_channels as f64,
-        sample_rate: wav_reader.sample_rate,
+        data_matrix: downmix_interleaved(&source.samples, source.num_channels as usize),
+        sample_rate: source.sample_rate,
     })
 }
 
+/// Given a `file_path` to an audio file on disk, this file is loaded and each channel is returned as a separate signal.
+pub fn load_channels(file_path: &str) -> Result<Vec<AudioSignal>, Box<dyn Error>> {
+    let source = AudioSource::open(file_path)?;
+    Ok(split_channels(&source))
+}
+
+/// Given a `file_path` to a file with headerless raw PCM data laid out as described by `format`, this file is loaded and each channel is returned as a separate signal.
+pub fn load_raw_channels(
+    file_path: &str,
+    format: &RawFormat,
+) -> Result<Vec<AudioSignal>, Box<dyn Error>> {
+    let source = AudioSource::open_raw(file_path, format)?;
+    Ok(split_channels(&source))
+}
+
+/// De-interleaves the samples of `source` into 1 signal per channel.
+fn split_channels(source: &AudioSource) -> Vec<AudioSignal> {
+    let channel_matrix = extract_multichannel(source.num_channels as usize, &source.samples);
+    channel_matrix
+        .columns()
+        .into_iter()
+        .map(|channel| AudioSignal {
+            data_matrix: channel.to_owned(),
+            sample_rate: source.sample_rate,
+        })
+        .collect()
+}
+
+/// Sums the channels of an interleaved signal with `num_channels` channels and normalizes them to 1 mono channel.
+pub fn downmix_interleaved(interleaved_vector: &[f64], num_channels: usize) -> Array1<f64> {
+    let final_signal = extract_multichannel(num_channels, interleaved_vector);
+    to_mono_matrix(&final_signal) / num_channels as f64
+}
+
 /// De-interleave an interleaved signal and returns them in a matrix. 1 row represents 1 channel.
 fn extract_multichannel(num_channels: usize, interleaved_vector: &[f64]) -> Array2<f64> {
     assert!(interleaved_vector.len().is_multiple_of(num_channels));
@@ -71,17 +134,18 @@ fn extract_multichannel(num_channels: usize, interleaved_vector: &[f64]) -> Arra
     .expect("Failed to sum multichannel signal to mono signal!")
 }
 
-/// Scales 2 spectrograms to match their sound pressure levels.
+/// Scales 2 spectrograms to match their sound pressure levels, applying `noise_floor`.
 pub fn prepare_spectrograms_for_comparison(
     reference: &mut Spectrogram,
     degraded: &mut Spectrogram,
+    noise_floor: &NoiseFloor,
 ) {
     reference.convert_to_db();
     degraded.convert_to_db();
-    reference.raise_floor(NOISE_FLOOR_ABSOLUTE_DB);
-    degraded.raise_floor(NOISE_FLOOR_ABSOLUTE_DB);
+    reference.raise_floor(noise_floor.absolute_db);
+    degraded.raise_floor(noise_floor.absolute_db);
 
-    reference.raise_floor_per_frame(NOISE_FLOOR_RELATIVE_TO_PEAK_DB, degraded);
+    reference.raise_floor_per_frame(noise_floor.relative_to_peak_db, degraded);
 
     let ref_floor = reference.get_minimum();
     let deg_floor = degraded.get_minimum();
@@ -175,14 +239,78 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
-    fn loading_32_bit_quantization_fails() {
-        load_as_mono("test_data/clean_speech/CA01_01_32bits.wav").unwrap();
+    fn stereo_file_is_split_into_channels() {
+        let file_path = "test_data/conformance_testdata_subset/guitar48_stereo.wav";
+        let channels = load_channels(file_path).unwrap();
+        let mono = load_as_mono(file_path).unwrap();
+
+        assert_eq!(channels.len(), 2);
+        for channel in &channels {
+            assert_eq!(channel.sample_rate, mono.sample_rate);
+            assert_eq!(channel.len(), mono.len());
+        }
+        for i in [0, 1000, 597782] {
+            assert_abs_diff_eq!(
+                (channels[0][i] + channels[1][i]) / 2.0,
+                mono[i],
+                epsilon = 1e-12
+            );
+        }
+    }
+
+    #[test]
+    fn loading_32_bit_float_quantization_matches_16_bit() {
+        let signal_16 = load_as_mono("test_data/clean_speech/CA01_01.wav").unwrap();
+        let signal_32 = load_as_mono("test_data/clean_speech/CA01_01_32bits.wav").unwrap();
+        assert_eq!(signal_32.sample_rate, signal_16.sample_rate);
+        assert_eq!(signal_32.len(), signal_16.len());
+        for (a, b) in signal_32
+            .data_matrix
+            .iter()
+            .zip(signal_16.data_matrix.iter())
+        {
+            assert_abs_diff_eq!(a, b, epsilon = 0.0001);
+        }
+    }
+
+    #[test]
+    fn loading_8_bit_quantization_matches_16_bit() {
+        let signal_16 = load_as_mono("test_data/clean_speech/CA01_01.wav").unwrap();
+        let signal_8 = load_as_mono("test_data/clean_speech/CA01_01_8bits.wav").unwrap();
+        assert_eq!(signal_8.sample_rate, signal_16.sample_rate);
+        assert_eq!(signal_8.len(), signal_16.len());
+        for (a, b) in signal_8
+            .data_matrix
+            .iter()
+            .zip(signal_16.data_matrix.iter())
+        {
+            assert_abs_diff_eq!(a, b, epsilon = 0.02);
+        }
     }
 
     #[test]
-    #[should_panic]
-    fn loading_8_bit_quantization_fails() {
-        load_as_mono("test_data/clean_speech/CA01_01_8bits.wav").unwrap();
+    fn loading_24_bit_quantization_matches_16_bit() {
+        let path = std::env::temp_dir().join("visqol_rs_CA01_01_24bits.wav");
+        let reference = hound::WavReader::open("test_data/clean_speech/CA01_01.wav").unwrap();
+        let spec = hound::WavSpec {
+            bits_per_sample: 24,
+            ..reference.spec()
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for sample in reference.into_samples::<i32>() {
+            writer.write_sample(sample.unwrap() << 8).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let signal_16 = load_as_mono("test_data/clean_speech/CA01_01.wav").unwrap();
+        let signal_24 = load_as_mono(path.to_str().unwrap()).unwrap();
+        assert_eq!(signal_24.len(), signal_16.len());
+        for (a, b) in signal_24
+            .data_matrix
+            .iter()
+            .zip(signal_16.data_matrix.iter())
+        {
+            assert_abs_diff_eq!(a, b, epsilon = 0.0001);
+        }
     }
 }
@@ -0,0 +1,144 @@
+use crate::{
+    patch_similarity_comparator::PatchSimilarityResult,
+    similarity_to_quality_mapper::SimilarityToQualityMapper, visqol, visqol_error::VisqolError,
+};
+use serde::Serialize;
+
+/// Spread of the MOS over patch sets resampled from the compared patches
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct MosConfidenceInterval {
+    /// Lower bound of the confidence interval
+    pub lower: f64,
+    /// Upper bound of the confidence interval
+    pub upper: f64,
+    /// Probability that the interval contains the MOS, e.g. 0.95
+    pub confidence_level: f64,
+    /// Standard deviation of the resampled MOS
+    pub standard_error: f64,
+    /// Number of resampled patch sets
+    pub iterations: usize,
+}
+
+/// Small pseudo random number generator (SplitMix64), so that the intervals are reproducible for a given seed.
+pub(crate) struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub(crate) fn new(seed: u64) -> Self { Self { state: seed } }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a uniformly distributed index below `len`.
+    pub(crate) fn next_index(&mut self, len: usize) -> usize {
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64 * len as f64) as usize
+    }
+}
+
+/// Estimates the confidence interval of the MOS of `patch_sims` by drawing `iterations` patch sets of the same size with replacement.
+/// The features of each set are recomputed and mapped to a MOS with `mapper`; the interval spans the central `confidence_level` of these MOS.
+/// Returns `None` if there are no patches or iterations, or the error of `mapper` if it fails.
+pub fn estimate_confidence_interval(
+    patch_sims: &[PatchSimilarityResult],
+    frame_duration: f64,
+    mapper: &dyn SimilarityToQualityMapper,
+    iterations: usize,
+    confidence_level: f64,
+    seed: u64,
+) -> Result<Option<MosConfidenceInterval>, VisqolError> {
+    if patch_sims.is_empty() || iterations == 0 {
+        return Ok(None);
+    }
+
+    let mut rng = SplitMix64::new(seed);
+    let mut resampled = Vec::<PatchSimilarityResult>::with_capacity(patch_sims.len());
+    let mut moslqos = (0..iterations)
+        .map(|_| {
+            resampled.clear();
+            resampled.extend(
+                (0..patch_sims.len()).map(|_| patch_sims[rng.next_index(patch_sims.len())].clone()),
+            );
+            visqol::predict_quality(&resampled, frame_duration, mapper)
+        })
+        .collect::<Result<Vec<f64>, VisqolError>>()?;
+    moslqos.sort_by(|a, b| a.total_cmp(b));
+
+    let mean = moslqos.iter().sum::<f64>() / iterations as f64;
+    let variance = moslqos
+        .iter()
+        .map(|moslqo| (moslqo - mean).powi(2))
+        .sum::<f64>()
+        / (iterations - 1).max(1) as f64;
+    let tail = (1.0 - confidence_level) / 2.0;
+    Ok(Some(MosConfidenceInterval {
+        lower: percentile(&moslqos, tail),
+        upper: percentile(&moslqos, 1.0 - tail),
+        confidence_level,
+        standard_error: variance.sqrt(),
+        iterations,
+    }))
+}
+
+/// Returns the `fraction` percentile of the ascending `sorted_values`, interpolating linearly between neighbors.
+pub(crate) fn percentile(sorted_values: &[f64], fraction: f64) -> f64 {
+    let position = fraction.clamp(0.0, 1.0) * (sorted_values.len() - 1) as f64;
+    let below = position.floor() as usize;
+    let above = position.ceil() as usize;
+    sorted_values[below] + (sorted_values[above] - sorted_values[below]) * (position - below as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utility::{patch, LinearMapper};
+
+    #[test]
+    fn interval_contains_the_mos_and_shrinks_with_more_patches() {
+        let similarities = [0.9, 0.5, 0.8, 0.7, 0.95, 0.6];
+        let similar_patch = |similarity| patch(0.0, 0.0, vec![similarity; 2]);
+        let few = similarities.map(similar_patch);
+        let many = similarities
+            .repeat(8)
+            .into_iter()
+            .map(similar_patch)
+            .collect::<Vec<_>>();
+
+        let few_interval = estimate_confidence_interval(&few, 0.02, &LinearMapper, 500, 0.95, 1)
+            .unwrap()
+            .unwrap();
+        let many_interval = estimate_confidence_interval(&many, 0.02, &LinearMapper, 500, 0.95, 1)
+            .unwrap()
+            .unwrap();
+
+        let moslqo = visqol::predict_quality(&few, 0.02, &LinearMapper).unwrap();
+        assert!(few_interval.lower < moslqo && moslqo < few_interval.upper);
+        assert!(
+            many_interval.upper - many_interval.lower < few_interval.upper - few_interval.lower
+        );
+        assert!(many_interval.standard_error < few_interval.standard_error);
+        assert_eq!(
+            estimate_confidence_interval(&few, 0.02, &LinearMapper, 500, 0.95, 1).unwrap(),
+            Some(few_interval)
+        );
+        assert!(
+            estimate_confidence_interval(&few, 0.02, &LinearMapper, 0, 0.95, 1)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn percentile_interpolates() {
+        let values = [1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(percentile(&values, 0.0), 1.0);
+        assert_eq!(percentile(&values, 0.5), 3.0);
+        assert_eq!(percentile(&values, 0.625), 3.5);
+        assert_eq!(percentile(&values, 1.0), 5.0);
+    }
+}
@@ -0,0 +1,202 @@
+use crate::{audio_signal::AudioSignal, visqol_error::VisqolError};
+use ndarray::Array1;
+
+/// Determines how the channels of multichannel signals are compared.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ChannelMode {
+    /// Sums all channels to a single mono signal.
+    #[default]
+    Downmix,
+    /// Compares each channel of the reference signal to the same channel of the degraded signal.
+    PerChannel,
+    /// Compares the mid `(L + R) / 2` and side `(L - R) / 2` signals of stereo signals.
+    MidSide,
+    /// Compares only the given channels, counting from 0.
+    Select(Vec<usize>),
+}
+
+impl ChannelMode {
+    /// Combines the channels of `ref_channels` and `deg_channels` according to this mode.
+    /// Returns a label and the reference and degraded signal for each comparison.
+    pub fn pair_channels(
+        &self,
+        ref_channels: &[AudioSignal],
+        deg_channels: &[AudioSignal],
+    ) -> Result<Vec<(String, AudioSignal, AudioSignal)>, VisqolError> {
+        if ref_channels.is_empty() || deg_channels.is_empty() {
+            return Err(VisqolError::DifferentChannelCounts {
+                reference: ref_channels.len(),
+                degraded: deg_channels.len(),
+            });
+        }
+
+        match self {
+            ChannelMode::Downmix => Ok(vec![(
+                String::from("mono"),
+                downmix(ref_channels),
+                downmix(deg_channels),
+            )]),
+            ChannelMode::PerChannel => {
+                Self::validate_channel_counts(ref_channels, deg_channels)?;
+                Ok(ref_channels
+                    .iter()
+                    .zip(deg_channels)
+                    .enumerate()
+                    .map(|(index, (reference, degraded))| {
+                        (index.to_string(), reference.clone(), degraded.clone())
+                    })
+                    .collect())
+            }
+            ChannelMode::MidSide => {
+                for channels in [ref_channels, deg_channels] {
+                    if channels.len() != 2 {
+                        return Err(VisqolError::MidSideRequiresStereo {
+                            num_channels: channels.len(),
+                        });
+                    }
+                }
+                let (ref_mid, ref_side) = mid_side(&ref_channels[0], &ref_channels[1]);
+                let (deg_mid, deg_side) = mid_side(&deg_channels[0], &deg_channels[1]);
+                Ok(vec![
+                    (String::from("mid"), ref_mid, deg_mid),
+                    (String::from("side"), ref_side, deg_side),
+                ])
+            }
+            ChannelMode::Select(selected_channels) => {
+                if selected_channels.is_empty() {
+                    return Err(VisqolError::NoChannelsSelected);
+                }
+                Self::validate_channel_counts(ref_channels, deg_channels)?;
+                selected_channels
+                    .iter()
+                    .map(|&channel| {
+                        if channel >= ref_channels.len() {
+                            return Err(VisqolError::ChannelOutOfRange {
+                                channel,
+                                num_channels: ref_channels.len(),
+                            });
+                        }
+                        Ok((
+                            channel.to_string(),
+                            ref_channels[channel].clone(),
+                            deg_channels[channel].clone(),
+                        ))
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Ensures that reference and degraded signal have the same number of channels.
+    fn validate_channel_counts(
+        ref_channels: &[AudioSignal],
+        deg_channels: &[AudioSignal],
+    ) -> Result<(), VisqolError> {
+        if ref_channels.len() != deg_channels.len() {
+            return Err(VisqolError::DifferentChannelCounts {
+                reference: ref_channels.len(),
+                degraded: deg_channels.len(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Sums all channels and normalizes them to 1 mono channel.
+fn downmix(channels: &[AudioSignal]) -> AudioSignal {
+    let longest = channels.iter().map(AudioSignal::len).max().unwrap_or(0);
+    let mut sum = Array1::<f64>::zeros(longest);
+    for channel in channels {
+        sum.slice_mut(ndarray::s![..channel.len()])
+            .zip_mut_with(&channel.data_matrix, |a, b| *a += b);
+    }
+    AudioSignal {
+        data_matrix: sum / channels.len() as f64,
+        sample_rate: channels[0].sample_rate,
+    }
+}
+
+/// Returns the mid and side signals of a stereo pair.
+fn mid_side(left: &AudioSignal, right: &AudioSignal) -> (AudioSignal, AudioSignal) {
+    let mid = (&left.data_matrix + &right.data_matrix) / 2.0;
+    let side = (&left.data_matrix - &right.data_matrix) / 2.0;
+    (
+        AudioSignal {
+            data_matrix: mid,
+            sample_rate: left.sample_rate,
+        },
+        AudioSignal {
+            data_matrix: side,
+            sample_rate: left.sample_rate,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stereo(left: &[f64], right: &[f64]) -> Vec<AudioSignal> {
+        vec![
+            AudioSignal::new(left, 16000),
+            AudioSignal::new(right, 16000),
+        ]
+    }
+
+    #[test]
+    fn channels_are_paired_according_to_mode() {
+        let reference = stereo(&[1.0, 1.0], &[0.0, 1.0]);
+        let degraded = stereo(&[0.5, 0.5], &[1.0, 0.0]);
+
+        let downmix = ChannelMode::Downmix
+            .pair_channels(&reference, &degraded)
+            .unwrap();
+        assert_eq!(downmix.len(), 1);
+        assert_eq!(downmix[0].1.data_matrix.to_vec(), vec![0.5, 1.0]);
+        assert_eq!(downmix[0].2.data_matrix.to_vec(), vec![0.75, 0.25]);
+
+        let per_channel = ChannelMode::PerChannel
+            .pair_channels(&reference, &degraded)
+            .unwrap();
+        assert_eq!(per_channel.len(), 2);
+        assert_eq!(per_channel[1].0, "1");
+        assert_eq!(per_channel[1].2.data_matrix.to_vec(), vec![1.0, 0.0]);
+
+        let mid_side = ChannelMode::MidSide
+            .pair_channels(&reference, &degraded)
+            .unwrap();
+        assert_eq!(mid_side[0].0, "mid");
+        assert_eq!(mid_side[0].1.data_matrix.to_vec(), vec![0.5, 1.0]);
+        assert_eq!(mid_side[1].0, "side");
+        assert_eq!(mid_side[1].1.data_matrix.to_vec(), vec![0.5, 0.0]);
+        assert_eq!(mid_side[1].2.data_matrix.to_vec(), vec![-0.25, 0.25]);
+
+        let selected = ChannelMode::Select(vec![1])
+            .pair_channels(&reference, &degraded)
+            .unwrap();
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].0, "1");
+        assert_eq!(selected[0].1.data_matrix.to_vec(), vec![0.0, 1.0]);
+    }
+
+    #[test]
+    fn invalid_channel_layouts_are_rejected() {
+        let reference = stereo(&[1.0], &[0.0]);
+        let mono = vec![AudioSignal::new(&[1.0], 16000)];
+
+        assert!(ChannelMode::PerChannel
+            .pair_channels(&reference, &mono)
+            .is_err());
+        assert!(ChannelMode::MidSide.pair_channels(&mono, &mono).is_err());
+        assert!(ChannelMode::Select(vec![2])
+            .pair_channels(&reference, &reference)
+            .is_err());
+        assert!(matches!(
+            ChannelMode::Select(vec![]).pair_channels(&reference, &reference),
+            Err(VisqolError::NoChannelsSelected)
+        ));
+        assert!(ChannelMode::Downmix
+            .pair_channels(&reference, &mono)
+            .is_ok());
+    }
+}
@@ -0,0 +1,151 @@
+use crate::audio_signal::AudioSignal;
+use crate::constants::{MAX_CLOCK_DRIFT_PPM, MIN_CLOCK_DRIFT_LAG_POINTS};
+use crate::patch_similarity_comparator::PatchSimilarityResult;
+use serde::Serialize;
+
+/// Lag between the matched patches of the reference and degraded signal at one time of the reference
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct LagPoint {
+    /// Start of the reference patch in seconds
+    pub ref_time: f64,
+    /// Delay of the degraded patch relative to the reference patch in seconds, positive if the degraded patch is late
+    pub lag: f64,
+}
+
+/// Returns the lag of each matched patch, including the `global_delay` that was removed before the patches were matched.
+/// Reference patches without a match are skipped.
+pub fn calculate_lag_curve(
+    patch_sims: &[PatchSimilarityResult],
+    global_delay: f64,
+) -> Vec<LagPoint> {
+    patch_sims
+        .iter()
+        .filter(|patch| patch.is_matched())
+        .map(|patch| LagPoint {
+            ref_time: patch.ref_patch_start_time,
+            lag: patch.deg_patch_start_time - patch.ref_patch_start_time + global_delay,
+        })
+        .collect()
+}
+
+/// Estimates the clock drift of the degraded signal in parts per million from the slope of `lag_curve`.
+/// A positive drift means that the degraded signal falls behind the reference over time.
+/// The slope is the median of the slopes between all pairs of points, so that a few mismatched patches do not skew it.
+/// Returns `None` if the curve has fewer than 2 distinct reference times.
+pub fn estimate_drift_ppm(lag_curve: &[LagPoint]) -> Option<f64> {
+    let mut slopes = Vec::<f64>::new();
+    for (index, first) in lag_curve.iter().enumerate() {
+        for second in &lag_curve[index + 1..] {
+            let duration = second.ref_time - first.ref_time;
+            if duration != 0.0 {
+                slopes.push((second.lag - first.lag) / duration);
+            }
+        }
+    }
+    if slopes.is_empty() {
+        return None;
+    }
+
+    slopes.sort_by(|a, b| a.total_cmp(b));
+    Some(slopes[slopes.len() / 2] * 1e6)
+}
+
+/// Returns whether a clock drift of `drift_ppm` estimated from `lag_curve` is reliable enough to be compensated.
+/// Too few matched patches give a rough estimate, and sample clocks do not drift by more than a few hundred ppm,
+/// so larger estimates point to mismatched patches or a wrong sample rate rather than drift.
+pub fn is_compensable(lag_curve: &[LagPoint], drift_ppm: f64) -> bool {
+    lag_curve.len() >= MIN_CLOCK_DRIFT_LAG_POINTS
+        && drift_ppm != 0.0
+        && drift_ppm.abs() <= MAX_CLOCK_DRIFT_PPM
+}
+
+/// Resamples `deg_signal` to remove a clock drift of `drift_ppm` parts per million, interpolating linearly between samples.
+/// Signals with fewer than 2 samples are returned unchanged.
+pub fn compensate_drift(deg_signal: &AudioSignal, drift_ppm: f64) -> AudioSignal {
+    let rate = 1.0 + drift_ppm * 1e-6;
+    let samples = &deg_signal.data_matrix;
+    if samples.len() < 2 {
+        return deg_signal.clone();
+    }
+    let num_samples = ((samples.len() - 1) as f64 / rate).floor() as usize + 1;
+    let compensated = (0..num_samples)
+        .map(|index| {
+            let position = index as f64 * rate;
+            let previous = position.floor() as usize;
+            let fraction = position - previous as f64;
+            match samples.get(previous + 1) {
+                Some(next) => samples[previous] * (1.0 - fraction) + next * fraction,
+                None => samples[previous],
+            }
+        })
+        .collect::<Vec<f64>>();
+    AudioSignal::new(&compensated, deg_signal.sample_rate)
+}
+
+/// Returns the time of the drifting degraded signal that was moved to `time` by compensating a drift of `drift_ppm`, see `compensate_drift`.
+pub fn to_drifted_time(time: f64, drift_ppm: f64) -> f64 { time * (1.0 + drift_ppm * 1e-6) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn drift_is_robust_to_outliers() {
+        let mut lag_curve = (0..20)
+            .map(|index| LagPoint {
+                ref_time: index as f64,
+                lag: 0.1 + index as f64 * 50e-6,
+            })
+            .collect::<Vec<LagPoint>>();
+        lag_curve[7].lag = 0.5;
+        lag_curve[13].lag = -0.3;
+
+        assert_abs_diff_eq!(
+            estimate_drift_ppm(&lag_curve).unwrap(),
+            50.0,
+            epsilon = 1e-6
+        );
+        assert!(estimate_drift_ppm(&lag_curve[..1]).is_none());
+    }
+
+    #[test]
+    fn only_plausible_drift_is_compensable() {
+        let lag_curve = vec![
+            LagPoint {
+                ref_time: 0.0,
+                lag: 0.0,
+            };
+            MIN_CLOCK_DRIFT_LAG_POINTS
+        ];
+
+        assert!(is_compensable(&lag_curve, 100.0));
+        assert!(is_compensable(&lag_curve, -MAX_CLOCK_DRIFT_PPM));
+        assert!(!is_compensable(&lag_curve, 0.0));
+        assert!(!is_compensable(&lag_curve, 2000.0));
+        assert!(!is_compensable(&lag_curve[1..], 100.0));
+    }
+
+    #[test]
+    fn compensation_undoes_drift() {
+        // A ramp that runs 1000 ppm slow is restored by compensating a drift of 1000 ppm.
+        let drifted = (0..16000)
+            .map(|index| index as f64 / 1.001)
+            .collect::<Vec<f64>>();
+        let compensated = compensate_drift(&AudioSignal::new(&drifted, 16000), 1000.0);
+
+        assert_eq!(compensated.len(), 15984);
+        assert_abs_diff_eq!(to_drifted_time(10.0, 1000.0), 10.01, epsilon = 1e-12);
+        for (index, sample) in compensated.data_matrix.iter().enumerate() {
+            assert_abs_diff_eq!(*sample, index as f64, epsilon = 1e-9);
+        }
+
+        for samples in [&[][..], &[0.5]] {
+            let signal = AudioSignal::new(samples, 16000);
+            assert_eq!(
+                compensate_drift(&signal, 1000.0).data_matrix,
+                signal.data_matrix
+            );
+        }
+    }
+}
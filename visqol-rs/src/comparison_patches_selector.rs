@@ -1,23 +1,24 @@
 use std::error::Error;
 
 use crate::alignment::align_and_truncate;
-use crate::gammatone_spectrogram_builder::GammatoneSpectrogramBuilder;
 use crate::{
     analysis_window::AnalysisWindow,
     audio_signal::AudioSignal,
-    audio_utils,
-    neurogram_similiarity_index_measure::NeurogramSimiliarityIndexMeasure,
+    audio_utils::{self, NoiseFloor},
     patch_similarity_comparator::{PatchSimilarityComparator, PatchSimilarityResult},
     spectrogram_builder::SpectrogramBuilder,
     visqol_error::VisqolError,
 };
 use ndarray::{concatenate, s, Array1, Array2, Axis};
+/// Finds the degraded patches that match the reference patches best, using `sim_comparator` to score each pair of patches.
 pub struct ComparisonPatchesSelector {
-    sim_comparator: NeurogramSimiliarityIndexMeasure,
+    sim_comparator: Box<dyn PatchSimilarityComparator + Send>,
 }
 
 impl ComparisonPatchesSelector {
-    pub fn new(sim_comparator: NeurogramSimiliarityIndexMeasure) -> Self { Self { sim_comparator } }
+    pub fn new(sim_comparator: Box<dyn PatchSimilarityComparator + Send>) -> Self {
+        Self { sim_comparator }
+    }
 
     /// This function composes the most suitable patches in a degraded signal given a reference signal.
     pub fn find_most_optimal_deg_patches(
@@ -94,17 +95,18 @@ impl ComparisonPatchesSelector {
 
         // The for loop is used to find the offset which maximizes the similarity
         // score across all the patches.
-        // +1 for including last
-        for slide_offset in lower_limit..ref_patch_indices[last_index] + search_window as usize + 1
+        // +1 for including last. The frame offset for degraded start patch cannot be
+        // more than the number of frames in the degraded spectrogram.
+        let upper_limit = (ref_patch_indices[last_index] + search_window as usize + 1)
+            .min(num_frames_in_deg_spectro);
+        for (slide_offset, &similarity) in cumulative_similarity_dp[last_index]
+            .iter()
+            .enumerate()
+            .take(upper_limit)
+            .skip(lower_limit)
         {
-            if slide_offset >= num_frames_in_deg_spectro {
-                // The frame offset for degraded start patch cannot be more than the
-                // number of frames in the degraded spectrogram.
-                break;
-            }
-
-            if cumulative_similarity_dp[last_index][slide_offset] > max_similarity_score {
-                max_similarity_score = cumulative_similarity_dp[last_index][slide_offset];
+            if similarity > max_similarity_score {
+                max_similarity_score = similarity;
                 last_offset = slide_offset;
             }
         }
@@ -259,7 +261,7 @@ impl ComparisonPatchesSelector {
 
     /// Given an `AudioSignal` and the desired start and end times in seconds, this function returns a copy of the segment in the audio signal ranging from `start_time` to `end_time`
     pub fn slice(in_signal: &AudioSignal, start_time: f64, end_time: f64) -> AudioSignal {
-        let start_index = ((start_time * in_signal.sample_rate as f64) as usize).max(0);
+        let start_index = (start_time * in_signal.sample_rate as f64) as usize;
         let end_index =
             ((end_time * in_signal.sample_rate as f64) as usize).min(in_signal.data_matrix.len());
 
@@ -297,7 +299,7 @@ impl ComparisonPatchesSelector {
         window_beginning: usize,
         window_end: usize,
     ) -> Array2<f64> {
-        let first_real_frame = 0.max(window_beginning);
+        let first_real_frame = window_beginning;
         let last_real_frame = window_end.min(spectrogram_data.ncols());
 
         let mut deg_patch = spectrogram_data
@@ -317,13 +319,14 @@ impl ComparisonPatchesSelector {
     }
 
     /// Performs alignment on a per-patch level.
-    pub fn finely_align_and_recreate_patches<const NUM_BANDS: usize>(
+    pub fn finely_align_and_recreate_patches(
         &self,
         sim_results: &mut [PatchSimilarityResult],
         ref_signal: &AudioSignal,
         deg_signal: &AudioSignal,
-        spect_builder: &mut GammatoneSpectrogramBuilder<NUM_BANDS>,
+        spect_builder: &mut dyn SpectrogramBuilder,
         analysis_window: &AnalysisWindow,
+        noise_floor: &NoiseFloor,
     ) -> Result<Vec<PatchSimilarityResult>, Box<dyn Error>> {
         // Case: The patches are already matched.  Iterate over each pair.
         let mut realigned_results = Vec::<PatchSimilarityResult>::with_capacity(sim_results.len());
@@ -365,6 +368,7 @@ impl ComparisonPatchesSelector {
             audio_utils::prepare_spectrograms_for_comparison(
                 &mut ref_spectrogram,
                 &mut deg_spectrogram,
+                noise_floor,
             );
             // 5. Update the similarity result with the new patch.
 
@@ -470,7 +474,7 @@ mod tests {
         let search_window = 8;
 
         let sim_measurer = NeurogramSimiliarityIndexMeasure::default();
-        let selector = ComparisonPatchesSelector::new(sim_measurer);
+        let selector = ComparisonPatchesSelector::new(Box::new(sim_measurer));
 
         let res = selector
             .find_most_optimal_deg_patches(
@@ -504,7 +508,7 @@ mod tests {
         let search_window = 60;
 
         let sim_measurer = NeurogramSimiliarityIndexMeasure::default();
-        let selector = ComparisonPatchesSelector::new(sim_measurer);
+        let selector = ComparisonPatchesSelector::new(Box::new(sim_measurer));
 
         let res = selector
             .find_most_optimal_deg_patches(
@@ -544,7 +548,7 @@ mod tests {
         let search_window = 60;
 
         let sim_measurer = NeurogramSimiliarityIndexMeasure::default();
-        let selector = ComparisonPatchesSelector::new(sim_measurer);
+        let selector = ComparisonPatchesSelector::new(Box::new(sim_measurer));
 
         let res = selector
             .find_most_optimal_deg_patches(
@@ -594,7 +598,7 @@ mod tests {
         let search_window = 60;
 
         let sim_measurer = NeurogramSimiliarityIndexMeasure::default();
-        let selector = ComparisonPatchesSelector::new(sim_measurer);
+        let selector = ComparisonPatchesSelector::new(Box::new(sim_measurer));
 
         let res = selector
             .find_most_optimal_deg_patches(
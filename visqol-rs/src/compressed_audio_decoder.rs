@@ -0,0 +1,139 @@
+use crate::{audio_source::AudioSource, visqol_error::VisqolError};
+use std::{error::Error, fs::File};
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::DecoderOptions,
+    errors::Error as SymphoniaError,
+    formats::{FormatOptions, FormatReader, Packet},
+    io::MediaSourceStream,
+    meta::MetadataOptions,
+    probe::Hint,
+};
+
+/// Decodes the compressed audio file in `file_path` using `symphonia`.
+/// Opus streams are demuxed by `symphonia` and decoded by `ropus`.
+pub fn decode(file_path: &str) -> Result<AudioSource, Box<dyn Error>> {
+    let stream = MediaSourceStream::new(Box::new(File::open(file_path)?), Default::default());
+    // Gapless decoding removes the encoder delay and padding from the decoded signal.
+    let format_options = FormatOptions {
+        enable_gapless: true,
+        ..Default::default()
+    };
+    let mut reader = symphonia::default::get_probe()
+        .format(
+            &Hint::new(),
+            stream,
+            &format_options,
+            &MetadataOptions::default(),
+        )?
+        .format;
+
+    let track = reader
+        .default_track()
+        .ok_or(VisqolError::InvalidAudioStream {
+            reason: "no audio track found",
+        })?;
+    let track_id = track.id;
+    let codec_params = track.codec_params.clone();
+    let sample_rate = codec_params
+        .sample_rate
+        .ok_or(VisqolError::InvalidAudioStream {
+            reason: "unknown sample rate",
+        })?;
+    let num_channels = codec_params
+        .channels
+        .ok_or(VisqolError::InvalidAudioStream {
+            reason: "unknown channel layout",
+        })?
+        .count();
+
+    #[cfg(feature = "opus")]
+    if codec_params.codec == symphonia::core::codecs::CODEC_TYPE_OPUS {
+        let pre_skip = codec_params.delay.unwrap_or(0) as usize;
+        let samples = decode_opus(reader.as_mut(), track_id, num_channels, pre_skip)?;
+        return Ok(AudioSource {
+            num_channels: num_channels as u16,
+            sample_rate,
+            samples,
+        });
+    }
+
+    let mut decoder =
+        symphonia::default::get_codecs().make(&codec_params, &DecoderOptions::default())?;
+    let mut samples = Vec::new();
+    while let Some(packet) = next_packet(reader.as_mut(), track_id)? {
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            Err(SymphoniaError::DecodeError(reason)) => {
+                log::warn!("Skipping corrupt packet in {}: {}", file_path, reason);
+                continue;
+            }
+            Err(error) => return Err(Box::new(error)),
+        };
+        let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, *decoded.spec());
+        buffer.copy_interleaved_ref(decoded);
+        samples.extend(buffer.samples().iter().map(|&sample| sample as f64));
+    }
+
+    Ok(AudioSource {
+        num_channels: num_channels as u16,
+        sample_rate,
+        samples,
+    })
+}
+
+/// Decodes all packets of the Opus track `track_id` at 48 kHz.
+/// The Ogg demuxer does not trim the encoder delay of Opus streams, so the first `pre_skip` frames are dropped here.
+#[cfg(feature = "opus")]
+fn decode_opus(
+    reader: &mut dyn FormatReader,
+    track_id: u32,
+    num_channels: usize,
+    pre_skip: usize,
+) -> Result<Vec<f64>, Box<dyn Error>> {
+    use ropus::{Channels, DecodeMode, Decoder};
+
+    let channels = match num_channels {
+        1 => Channels::Mono,
+        2 => Channels::Stereo,
+        _ => {
+            return Err(Box::new(VisqolError::InvalidAudioStream {
+                reason: "only mono and stereo opus streams are supported",
+            }))
+        }
+    };
+    let mut decoder = Decoder::new(48000, channels)?;
+    let mut frame = vec![0.0f32; decoder.max_frame_samples_per_channel() * num_channels];
+
+    let mut samples = Vec::new();
+    while let Some(packet) = next_packet(reader, track_id)? {
+        let num_frames = decoder.decode_float(&packet.data, &mut frame, DecodeMode::Normal)?;
+        let end = num_frames.saturating_sub(packet.trim_end as usize);
+        samples.extend(
+            frame[..end * num_channels]
+                .iter()
+                .map(|&sample| sample as f64),
+        );
+    }
+    samples.drain(..(pre_skip * num_channels).min(samples.len()));
+    Ok(samples)
+}
+
+/// Returns the next packet of track `track_id`, or `None` at the end of the stream.
+fn next_packet(
+    reader: &mut dyn FormatReader,
+    track_id: u32,
+) -> Result<Option<Packet>, SymphoniaError> {
+    loop {
+        match reader.next_packet() {
+            Ok(packet) if packet.track_id() == track_id => return Ok(Some(packet)),
+            Ok(_) => continue,
+            Err(SymphoniaError::IoError(error))
+                if error.kind() == std::io::ErrorKind::UnexpectedEof =>
+            {
+                return Ok(None)
+            }
+            Err(error) => return Err(error),
+        }
+    }
+}
@@ -2,12 +2,29 @@ pub const DURATION_MISMATCH_TOLERANCE: f64 = 1.0;
 
 pub const NUM_BANDS_SPEECH: usize = 21;
 pub const NUM_BANDS_AUDIO: usize = 32;
+pub const NUM_BANDS_NARROWBAND: usize = 12;
+pub const NUM_BANDS_SUPER_WIDEBAND: usize = 25;
+pub const SAMPLE_RATE_SPEECH: u32 = 16000;
+pub const SAMPLE_RATE_AUDIO: u32 = 48000;
+pub const SAMPLE_RATE_NARROWBAND: u32 = 8000;
+pub const SAMPLE_RATE_SUPER_WIDEBAND: u32 = 32000;
 pub const DEFAULT_WINDOW_SIZE: usize = 32;
 
 pub const PATCH_SIZE_AUDIO: usize = 30;
 pub const PATCH_SIZE_SPEECH: usize = 20;
 pub const MINIMUM_FREQ: f64 = 50.0;
+pub const SPEECH_MODE_MAX_FREQ: f64 = 8000.0;
+pub const NARROWBAND_MIN_FREQ: f64 = 300.0;
+pub const NARROWBAND_MAX_FREQ: f64 = 3400.0;
+pub const SUPER_WIDEBAND_MAX_FREQ: f64 = 16000.0;
 pub const OVERLAP: f64 = 0.25;
 pub const WINDOW_DURATION: f64 = 0.08;
+pub const DEFAULT_MAX_TIME_WARP: f64 = 0.5;
+pub const MIN_CLOCK_DRIFT_LAG_POINTS: usize = 10;
+pub const MAX_CLOCK_DRIFT_PPM: f64 = 500.0;
+pub const DEFAULT_QUALITY_TRACE_PATCHES: usize = 1;
+pub const DEFAULT_BOOTSTRAP_CONFIDENCE_LEVEL: f64 = 0.95;
+pub const DEFAULT_BOOTSTRAP_SEED: u64 = 0;
+pub const DEFAULT_AB_BOOTSTRAP_ITERATIONS: usize = 1000;
 
 pub const NUM_FILTER_CONDITIONS: usize = 2;
@@ -1,5 +1,4 @@
 use crate::analysis_window::AnalysisWindow;
-use crate::constants::NUM_BANDS_SPEECH;
 use crate::equivalent_rectangular_bandwidth;
 use crate::gammatone_filterbank::GammatoneFilterbank;
 use crate::spectrogram::Spectrogram;
@@ -8,11 +7,11 @@ use crate::{audio_signal::AudioSignal, visqol_error::VisqolError};
 use ndarray::{Array2, Axis};
 
 /// Produces a frequency domain representation from a time domain signal using a gammatone filterbank.
-pub struct GammatoneSpectrogramBuilder<const NUM_BANDS: usize> {
-    filter_bank: GammatoneFilterbank<NUM_BANDS>,
+pub struct GammatoneSpectrogramBuilder {
+    filter_bank: GammatoneFilterbank,
 }
 
-impl<const NUM_BANDS: usize> SpectrogramBuilder for GammatoneSpectrogramBuilder<NUM_BANDS> {
+impl SpectrogramBuilder for GammatoneSpectrogramBuilder {
     fn build(
         &mut self,
         signal: &AudioSignal,
@@ -20,19 +19,17 @@ impl<const NUM_BANDS: usize> SpectrogramBuilder for GammatoneSpectrogramBuilder<
     ) -> Result<Spectrogram, VisqolError> {
         let time_domain_signal = &signal.data_matrix;
         let sample_rate = signal.sample_rate;
-        let max_freq = if NUM_BANDS == NUM_BANDS_SPEECH {
-            Self::SPEECH_MODE_MAX_FREQ
-        } else {
-            sample_rate / 2
-        };
+        let num_bands = self.filter_bank.num_bands;
+        // Bands above the Nyquist frequency cannot be represented in the signal.
+        let max_freq = self.filter_bank.max_freq.min(sample_rate as f64 / 2.0);
 
         // get gammatone coefficients
-        let (mut filter_coeffs, mut center_freqs) =
-            equivalent_rectangular_bandwidth::make_filters::<NUM_BANDS>(
-                sample_rate as usize,
-                self.filter_bank.min_freq,
-                max_freq as f64,
-            );
+        let (mut filter_coeffs, mut center_freqs) = equivalent_rectangular_bandwidth::make_filters(
+            sample_rate as usize,
+            num_bands,
+            self.filter_bank.min_freq,
+            max_freq,
+        );
         filter_coeffs.invert_axis(Axis(0));
         self.filter_bank.set_filter_coefficients(&filter_coeffs);
         self.filter_bank.reset_filter_conditions();
@@ -47,7 +44,7 @@ impl<const NUM_BANDS: usize> SpectrogramBuilder for GammatoneSpectrogramBuilder<
         }
 
         let num_cols = 1 + ((time_domain_signal.len() - window.size) / hop_size);
-        let mut out_matrix = Array2::<f64>::zeros((NUM_BANDS, num_cols));
+        let mut out_matrix = Array2::<f64>::zeros((num_bands, num_cols));
 
         for (index, frame) in time_domain_signal
             .windows(window.size)
@@ -85,12 +82,10 @@ impl<const NUM_BANDS: usize> SpectrogramBuilder for GammatoneSpectrogramBuilder<
     }
 }
 
-impl<const NUM_BANDS: usize> GammatoneSpectrogramBuilder<NUM_BANDS> {
-    const SPEECH_MODE_MAX_FREQ: u32 = 8000;
-
+impl GammatoneSpectrogramBuilder {
     /// Creates a new gammatone spectrogram builder with the given gammatone filterbank.
-    /// If `use_speech_mode` is set to `true`, the maximum frequency is determined to be 8000 Hz.
-    pub fn new(filter_bank: GammatoneFilterbank<NUM_BANDS>) -> Self { Self { filter_bank } }
+    /// The bands of the spectrogram are those of the filterbank.
+    pub fn new(filter_bank: GammatoneFilterbank) -> Self { Self { filter_bank } }
 }
 
 #[cfg(test)]
@@ -114,11 +109,11 @@ mod tests {
             "test_data/conformance_testdata_subset/contrabassoon48_stereo.wav",
         )
         .unwrap();
-        let filter_bank = GammatoneFilterbank::<{ NUM_BANDS }>::new(MINIMUM_FREQ);
+        let filter_bank =
+            GammatoneFilterbank::new(NUM_BANDS, MINIMUM_FREQ, signal_ref.sample_rate as f64 / 2.0);
         let window = AnalysisWindow::new(signal_ref.sample_rate, OVERLAP, 0.08);
 
-        let mut spectro_builder: GammatoneSpectrogramBuilder<NUM_BANDS> =
-            GammatoneSpectrogramBuilder::new(filter_bank);
+        let mut spectro_builder = GammatoneSpectrogramBuilder::new(filter_bank);
         let spectrogram_ref = spectro_builder.build(&signal_ref, &window).unwrap();
 
         // Check 1st element
@@ -126,4 +121,23 @@ mod tests {
         // Check dimensions
         assert_eq!(spectrogram_ref.data.ncols(), REF_SPECTRO_NUM_COLS);
     }
+
+    #[test]
+    fn max_freq_is_limited_to_nyquist() {
+        let signal =
+            audio_utils::load_as_mono("test_data/clean_speech/reference_signal_16k.wav").unwrap();
+        let window = AnalysisWindow::new(signal.sample_rate, 0.25, 0.08);
+        let nyquist = signal.sample_rate as f64 / 2.0;
+
+        let mut above_nyquist =
+            GammatoneSpectrogramBuilder::new(GammatoneFilterbank::new(21, 50.0, 20000.0));
+        let mut at_nyquist =
+            GammatoneSpectrogramBuilder::new(GammatoneFilterbank::new(21, 50.0, nyquist));
+        let clamped = above_nyquist.build(&signal, &window).unwrap();
+        let expected = at_nyquist.build(&signal, &window).unwrap();
+
+        assert!(clamped.center_freq_bands.iter().all(|&freq| freq < nyquist));
+        assert_eq!(clamped.center_freq_bands, expected.center_freq_bands);
+        assert_eq!(clamped.data, expected.data);
+    }
 }
@@ -0,0 +1,367 @@
+use crate::audio_signal::AudioSignal;
+use crate::patch_similarity_comparator::PatchSimilarityResult;
+use crate::xcorr;
+use serde::Serialize;
+use std::convert::TryFrom;
+
+/// Shortest impairment in seconds that is reported, shorter lag changes are attributed to alignment jitter
+const MIN_IMPAIRMENT_DURATION: f64 = 0.02;
+/// Samples below this absolute amplitude are considered digital silence
+const SILENCE_AMPLITUDE: f64 = 1e-5;
+/// Reference segments with an RMS above this level are considered active
+const ACTIVE_RMS: f64 = 1e-3;
+/// Inserted audio that correlates at least this well with the audio right before it is considered repeated
+const REPETITION_CORRELATION: f64 = 0.7;
+
+/// Kind of a timing impairment of the degraded signal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImpairmentKind {
+    /// Reference audio is missing from the degraded signal, e.g. a dropout or buffer underrun
+    Missing,
+    /// The degraded signal repeats the audio before it, e.g. packet loss concealment or a repeated frame
+    Repeated,
+    /// The degraded signal contains audio that is not in the reference, e.g. silence inserted by a jitter buffer
+    Inserted,
+}
+
+/// A time range in which the degraded signal deviates from the timeline of the reference.
+/// Missing audio has an empty degraded range, inserted and repeated audio has an empty reference range.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Impairment {
+    /// Kind of the impairment
+    pub kind: ImpairmentKind,
+    /// Start of the affected reference audio in seconds
+    pub ref_start_time: f64,
+    /// End of the affected reference audio in seconds
+    pub ref_end_time: f64,
+    /// Start of the affected degraded audio in seconds
+    pub deg_start_time: f64,
+    /// End of the affected degraded audio in seconds
+    pub deg_end_time: f64,
+}
+
+impl Impairment {
+    /// Duration of the impairment in seconds
+    pub fn duration(&self) -> f64 {
+        (self.ref_end_time - self.ref_start_time).max(self.deg_end_time - self.deg_start_time)
+    }
+}
+
+/// Lists the time ranges in which audio is missing from, repeated in or inserted into `deg_signal`, ordered by reference time.
+/// Missing audio is found in reference patches without a match and in digital silence of the degraded signal where the reference is active.
+/// Inserted and repeated audio, as well as further missing audio, is found in the jumps of the lag between consecutive matched patches.
+/// Both signals are expected on the timeline of `patch_sims`.
+pub fn detect_impairments(
+    patch_sims: &[PatchSimilarityResult],
+    ref_signal: &AudioSignal,
+    deg_signal: &AudioSignal,
+) -> Vec<Impairment> {
+    let mut impairments = Vec::<Impairment>::new();
+    let mut previous: Option<&PatchSimilarityResult> = None;
+
+    for patch in patch_sims {
+        if !patch.is_matched() {
+            // The reference patch has no counterpart, place it where the last lag puts it.
+            let lag = previous.map_or(0.0, lag_of);
+            impairments.push(Impairment {
+                kind: ImpairmentKind::Missing,
+                ref_start_time: patch.ref_patch_start_time,
+                ref_end_time: patch.ref_patch_end_time,
+                deg_start_time: patch.ref_patch_start_time + lag,
+                deg_end_time: patch.ref_patch_start_time + lag,
+            });
+            continue;
+        }
+
+        if let Some(previous) = previous {
+            let lag_change = lag_of(patch) - lag_of(previous);
+            if lag_change.abs() >= MIN_IMPAIRMENT_DURATION {
+                let deg_time = locate_lag_change(ref_signal, deg_signal, previous, patch);
+                let ref_time = deg_time - lag_of(previous);
+                impairments.push(if lag_change > 0.0 {
+                    let (kind, deg_time) =
+                        classify_insertion(deg_signal, deg_time, deg_time + lag_change);
+                    let ref_time = deg_time - lag_of(previous);
+                    Impairment {
+                        kind,
+                        ref_start_time: ref_time,
+                        ref_end_time: ref_time,
+                        deg_start_time: deg_time,
+                        deg_end_time: deg_time + lag_change,
+                    }
+                } else {
+                    Impairment {
+                        kind: ImpairmentKind::Missing,
+                        ref_start_time: ref_time,
+                        ref_end_time: ref_time - lag_change,
+                        deg_start_time: deg_time,
+                        deg_end_time: deg_time,
+                    }
+                });
+            }
+        }
+        impairments.extend(find_silent_dropouts(patch, ref_signal, deg_signal));
+        previous = Some(patch);
+    }
+
+    impairments.sort_by(|a, b| a.ref_start_time.total_cmp(&b.ref_start_time));
+    merge_overlapping(impairments)
+}
+
+/// Returns the delay of the degraded patch relative to the reference patch in seconds.
+fn lag_of(patch: &PatchSimilarityResult) -> f64 {
+    patch.deg_patch_start_time - patch.ref_patch_start_time
+}
+
+/// Finds the degraded time at which the lag of `before` changes to the lag of `after`, somewhere between the starts of both degraded patches.
+/// The degraded samples before this time should match the reference at the first lag, the samples after it and any inserted audio at the second lag.
+/// Returns the time that minimizes the squared error of both parts.
+fn locate_lag_change(
+    ref_signal: &AudioSignal,
+    deg_signal: &AudioSignal,
+    before: &PatchSimilarityResult,
+    after: &PatchSimilarityResult,
+) -> f64 {
+    let sample_rate = deg_signal.sample_rate as f64;
+    let to_index =
+        |time: f64| ((time.max(0.0) * sample_rate).round() as usize).min(deg_signal.len());
+    let first = to_index(before.deg_patch_start_time);
+    let last = to_index(after.deg_patch_end_time).max(first);
+    let inserted = ((lag_of(after) - lag_of(before)).max(0.0) * sample_rate).round() as usize;
+
+    // Cumulative squared errors between the degraded samples and the reference at either lag.
+    let cumulative_error = |lag: f64| {
+        let shift = (lag * sample_rate).round() as i64;
+        let mut cumulative = Vec::<f64>::with_capacity(last - first + 1);
+        cumulative.push(0.0);
+        for index in first..last {
+            let reference = usize::try_from(index as i64 - shift)
+                .ok()
+                .and_then(|ref_index| ref_signal.data_matrix.get(ref_index))
+                .copied()
+                .unwrap_or(0.0);
+            let error = (deg_signal.data_matrix[index] - reference).powi(2);
+            cumulative.push(cumulative[cumulative.len() - 1] + error);
+        }
+        cumulative
+    };
+    let error_before = cumulative_error(lag_of(before));
+    let error_after = cumulative_error(lag_of(after));
+    let total_after = error_after[error_after.len() - 1];
+
+    let best_offset = (0..=(last - first).saturating_sub(inserted))
+        .min_by(|&a, &b| {
+            let cost =
+                |offset: usize| error_before[offset] + total_after - error_after[offset + inserted];
+            cost(a).total_cmp(&cost(b))
+        })
+        .unwrap_or(0);
+    (first + best_offset) as f64 / sample_rate
+}
+
+/// Returns the samples of `signal` from `start_time` to `end_time`, limited to the signal.
+fn segment(signal: &AudioSignal, start_time: f64, end_time: f64) -> &[f64] {
+    let to_index = |time: f64| {
+        ((time.max(0.0) * signal.sample_rate as f64).round() as usize).min(signal.len())
+    };
+    let samples = signal
+        .data_matrix
+        .as_slice()
+        .expect("Failed to get samples of signal!");
+    &samples[to_index(start_time)..to_index(end_time).max(to_index(start_time))]
+}
+
+/// Decides whether the degraded audio from `start_time` to `end_time` repeats the audio next to it and returns its kind and start time.
+/// Repeated audio fits equally well at every copy, so if it matches the audio right after it, the later copy is reported as the repetition.
+fn classify_insertion(
+    deg_signal: &AudioSignal,
+    start_time: f64,
+    end_time: f64,
+) -> (ImpairmentKind, f64) {
+    let duration = end_time - start_time;
+    let inserted = segment(deg_signal, start_time, end_time);
+    let is_copy = |other: &[f64]| {
+        other.len() == inserted.len()
+            && xcorr::calculate_correlation_at_lag(other, inserted, 0)
+                .is_some_and(|correlation| correlation >= REPETITION_CORRELATION)
+    };
+
+    if is_copy(segment(deg_signal, start_time - duration, start_time)) {
+        (ImpairmentKind::Repeated, start_time)
+    } else if is_copy(segment(deg_signal, end_time, end_time + duration)) {
+        (ImpairmentKind::Repeated, end_time)
+    } else {
+        (ImpairmentKind::Inserted, start_time)
+    }
+}
+
+/// Finds runs of digital silence in the degraded audio of a matched patch during which the reference is active.
+fn find_silent_dropouts(
+    patch: &PatchSimilarityResult,
+    ref_signal: &AudioSignal,
+    deg_signal: &AudioSignal,
+) -> Vec<Impairment> {
+    let sample_rate = deg_signal.sample_rate as f64;
+    let min_samples = (MIN_IMPAIRMENT_DURATION * sample_rate).ceil() as usize;
+    let lag = lag_of(patch);
+    let deg_samples = segment(
+        deg_signal,
+        patch.deg_patch_start_time,
+        patch.deg_patch_end_time,
+    );
+    let first_sample = (patch.deg_patch_start_time.max(0.0) * sample_rate).round() as usize;
+
+    let mut dropouts = Vec::<Impairment>::new();
+    let mut add_if_dropout = |run_start: usize, run_end: usize| {
+        if run_end - run_start < min_samples {
+            return;
+        }
+        let deg_start_time = (first_sample + run_start) as f64 / sample_rate;
+        let deg_end_time = (first_sample + run_end) as f64 / sample_rate;
+        let ref_samples = segment(ref_signal, deg_start_time - lag, deg_end_time - lag);
+        if ref_samples.is_empty() {
+            return;
+        }
+        let ref_rms = (ref_samples
+            .iter()
+            .map(|sample| sample * sample)
+            .sum::<f64>()
+            / ref_samples.len() as f64)
+            .sqrt();
+        if ref_rms > ACTIVE_RMS {
+            dropouts.push(Impairment {
+                kind: ImpairmentKind::Missing,
+                ref_start_time: deg_start_time - lag,
+                ref_end_time: deg_end_time - lag,
+                deg_start_time,
+                deg_end_time,
+            });
+        }
+    };
+
+    let mut run_start = None;
+    for (index, sample) in deg_samples.iter().enumerate() {
+        match (sample.abs() < SILENCE_AMPLITUDE, run_start) {
+            (true, None) => run_start = Some(index),
+            (false, Some(start)) => {
+                add_if_dropout(start, index);
+                run_start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(start) = run_start {
+        add_if_dropout(start, deg_samples.len());
+    }
+    dropouts
+}
+
+/// Joins impairments of the same kind whose reference ranges overlap or touch, e.g. a dropout that spans several overlapping patches.
+fn merge_overlapping(impairments: Vec<Impairment>) -> Vec<Impairment> {
+    let mut merged = Vec::<Impairment>::with_capacity(impairments.len());
+    for impairment in impairments {
+        if let Some(last) = merged.last_mut().filter(|last: &&mut Impairment| {
+            last.kind == impairment.kind
+                && impairment.ref_start_time <= last.ref_end_time
+                && impairment.deg_start_time <= last.deg_end_time
+        }) {
+            last.ref_end_time = last.ref_end_time.max(impairment.ref_end_time);
+            last.deg_end_time = last.deg_end_time.max(impairment.deg_end_time);
+        } else {
+            merged.push(impairment);
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utility::patch;
+    use approx::assert_abs_diff_eq;
+    use std::f64::consts::PI;
+
+    const SAMPLE_RATE: u32 = 16000;
+
+    fn tone(duration: f64) -> Vec<f64> {
+        (0..(duration * SAMPLE_RATE as f64) as usize)
+            .map(|n| 0.5 * (2.0 * PI * 437.0 * n as f64 / SAMPLE_RATE as f64).sin())
+            .collect()
+    }
+
+    #[test]
+    fn unmatched_patches_and_silence_are_missing() {
+        let reference = AudioSignal::new(&tone(2.0), SAMPLE_RATE);
+        let mut degraded_samples = tone(2.0);
+        degraded_samples[4000..4800]
+            .iter_mut()
+            .for_each(|sample| *sample = 0.0);
+        let degraded = AudioSignal::new(&degraded_samples, SAMPLE_RATE);
+        let patch_sims = vec![
+            patch(0.0, 0.0, vec![0.9; 2]),
+            PatchSimilarityResult {
+                ref_patch_start_time: 0.5,
+                ref_patch_end_time: 1.0,
+                ..Default::default()
+            },
+            patch(1.0, 1.0, vec![0.9; 2]),
+        ];
+
+        let impairments = detect_impairments(&patch_sims, &reference, &degraded);
+
+        assert_eq!(impairments.len(), 2);
+        assert_eq!(impairments[0].kind, ImpairmentKind::Missing);
+        assert_abs_diff_eq!(impairments[0].deg_start_time, 0.25, epsilon = 1e-9);
+        assert_abs_diff_eq!(impairments[0].duration(), 0.05, epsilon = 1e-9);
+        assert_eq!(impairments[1].kind, ImpairmentKind::Missing);
+        assert_abs_diff_eq!(impairments[1].ref_start_time, 0.5, epsilon = 1e-9);
+        assert_abs_diff_eq!(impairments[1].duration(), 0.5, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn lag_jumps_are_insertions_or_repetitions() {
+        let reference = AudioSignal::new(&tone(2.0), SAMPLE_RATE);
+        // 100 ms of noise-free silence after 0.5 s, then a repetition of the preceding 40 ms after 1.0 s.
+        let mut degraded_samples = tone(0.5);
+        degraded_samples.extend(vec![0.0; 1600]);
+        degraded_samples.extend(&tone(1.0)[8000..16000]);
+        degraded_samples.extend_from_within(degraded_samples.len() - 640..);
+        degraded_samples.extend(&tone(2.0)[16000..]);
+        let degraded = AudioSignal::new(&degraded_samples, SAMPLE_RATE);
+        let patch_sims = vec![
+            patch(0.0, 0.0, vec![0.9; 2]),
+            patch(0.6, 0.7, vec![0.9; 2]),
+            patch(1.2, 1.34, vec![0.9; 2]),
+        ];
+
+        let impairments = detect_impairments(&patch_sims, &reference, &degraded);
+
+        assert_eq!(impairments.len(), 2);
+        assert_eq!(impairments[0].kind, ImpairmentKind::Inserted);
+        assert_abs_diff_eq!(impairments[0].deg_start_time, 0.5, epsilon = 1e-9);
+        assert_abs_diff_eq!(impairments[0].duration(), 0.1, epsilon = 1e-9);
+        assert_eq!(impairments[1].kind, ImpairmentKind::Repeated);
+        assert_abs_diff_eq!(impairments[1].deg_start_time, 1.1, epsilon = 1e-9);
+        assert_abs_diff_eq!(impairments[1].ref_start_time, 1.0, epsilon = 1e-9);
+        assert_abs_diff_eq!(impairments[1].duration(), 0.04, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn lag_drops_are_missing() {
+        let reference = AudioSignal::new(&tone(2.0), SAMPLE_RATE);
+        // 100 ms of the reference are skipped after 0.7 s.
+        let mut degraded_samples = tone(2.0);
+        degraded_samples.drain(11200..12800);
+        let degraded = AudioSignal::new(&degraded_samples, SAMPLE_RATE);
+        let patch_sims = vec![patch(0.5, 0.5, vec![0.9; 2]), patch(1.0, 0.9, vec![0.9; 2])];
+
+        let impairments = detect_impairments(&patch_sims, &reference, &degraded);
+
+        assert_eq!(impairments.len(), 1);
+        assert_eq!(impairments[0].kind, ImpairmentKind::Missing);
+        assert_abs_diff_eq!(impairments[0].ref_start_time, 0.7, epsilon = 1e-9);
+        assert_abs_diff_eq!(impairments[0].ref_end_time, 0.8, epsilon = 1e-9);
+        assert_abs_diff_eq!(impairments[0].deg_start_time, 0.7, epsilon = 1e-9);
+    }
+}
@@ -0,0 +1,196 @@
+use crate::{
+    constants::NUM_BANDS_SPEECH, similarity_to_quality_mapper::SimilarityToQualityMapper,
+    visqol_error::VisqolError,
+};
+use tract_onnx::prelude::*;
+
+/// Number of per-band features the lattice model consumes: fvnsim, fvnsim10, fstdnsim and fvdegenergy.
+const NUM_FEATURES_PER_BAND: usize = 4;
+/// Index of the `tau` input, which follows the per-band features.
+const TAU_INDEX: usize = NUM_BANDS_SPEECH * NUM_FEATURES_PER_BAND;
+/// Value of the `tau` input. The model predicts the median MOS at 0.5.
+const TAU: f32 = 0.5;
+
+/// Maps similarity features to a MOS using the lattice model of ViSQOL v3.3, evaluated on the CPU with `tract`.
+pub struct LatticeSimilarityToQualityMapper {
+    model: TypedRunnableModel<TypedModel>,
+    /// Feature index (the `k` in `Placeholder_k`) of each model input, in the order the model expects its inputs.
+    input_feature_indices: Vec<usize>,
+}
+
+impl LatticeSimilarityToQualityMapper {
+    /// Loads the ONNX lattice model located in `model_path`.
+    /// Returns an error if the model cannot be read or has inputs other than the per-band features and `tau`.
+    pub fn new(model_path: &str) -> Result<Self, VisqolError> {
+        let load_error = |reason: String| VisqolError::FailedToLoadModel {
+            model_path: model_path.to_string(),
+            reason,
+        };
+        let mut model = tract_onnx::onnx()
+            .model_for_path(model_path)
+            .map_err(|error| load_error(error.to_string()))?;
+
+        let input_outlets = model
+            .input_outlets()
+            .map_err(|error| load_error(error.to_string()))?
+            .to_vec();
+        let mut input_feature_indices = Vec::with_capacity(input_outlets.len());
+        for (input, outlet) in input_outlets.iter().enumerate() {
+            let input_name = &model.node(outlet.node).name;
+            let feature_index = Self::feature_index(input_name)
+                .filter(|&index| index <= TAU_INDEX)
+                .ok_or_else(|| load_error(format!("unexpected input {}", input_name)))?;
+            input_feature_indices.push(feature_index);
+            model
+                .set_input_fact(input, f32::fact([1]).into())
+                .map_err(|error| load_error(error.to_string()))?;
+        }
+
+        // Decluttering the lattice ensemble takes several seconds, far longer than evaluating the plain typed model.
+        let model = model
+            .into_typed()
+            .and_then(|model| model.into_runnable())
+            .map_err(|error| load_error(error.to_string()))?;
+        Ok(Self {
+            model,
+            input_feature_indices,
+        })
+    }
+
+    /// Parses the feature index from an input name like `Placeholder_12:0`.
+    fn feature_index(input_name: &str) -> Option<usize> {
+        let name = input_name.split(':').next().unwrap_or_default();
+        match name.strip_prefix("Placeholder")? {
+            "" => Some(0),
+            suffix => suffix.trim_start_matches('_').parse().ok(),
+        }
+    }
+}
+
+impl SimilarityToQualityMapper for LatticeSimilarityToQualityMapper {
+    fn predict_quality(
+        &self,
+        fvnsim: &[f64],
+        fvnsim10: &[f64],
+        fstdnsim: &[f64],
+        fvdegenergy: &[f64],
+    ) -> Result<f64, VisqolError> {
+        let features = [fvnsim, fvnsim10, fstdnsim, fvdegenergy];
+        if let Some(feature) = features
+            .iter()
+            .find(|feature| feature.len() != NUM_BANDS_SPEECH)
+        {
+            return Err(VisqolError::WrongNumberOfBands {
+                required: NUM_BANDS_SPEECH,
+                found: feature.len(),
+            });
+        }
+
+        let inputs = self
+            .input_feature_indices
+            .iter()
+            .map(|&index| {
+                let value = if index == TAU_INDEX {
+                    TAU
+                } else {
+                    let band = index / NUM_FEATURES_PER_BAND;
+                    features[index % NUM_FEATURES_PER_BAND][band] as f32
+                };
+                tensor1(&[value]).into()
+            })
+            .collect::<TVec<TValue>>();
+
+        let prediction_error = |error: TractError| VisqolError::FailedToPredictQuality {
+            reason: error.to_string(),
+        };
+        let outputs = self.model.run(inputs).map_err(prediction_error)?;
+        let mos = outputs[0].as_slice::<f32>().map_err(prediction_error)?[0] as f64;
+        Ok(mos.clamp(1.0, 5.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    const MODEL_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../model/model.onnx");
+
+    #[test]
+    fn lower_similarity_predicts_lower_mos() {
+        let mapper = LatticeSimilarityToQualityMapper::new(MODEL_PATH).unwrap();
+        let fstdnsim = [0.05; NUM_BANDS_SPEECH];
+        let fvdegenergy = [20.0; NUM_BANDS_SPEECH];
+
+        let predictions = [1.0, 0.9, 0.7]
+            .iter()
+            .map(|&nsim| {
+                let fvnsim = [nsim; NUM_BANDS_SPEECH];
+                mapper
+                    .predict_quality(&fvnsim, &fvnsim, &fstdnsim, &fvdegenergy)
+                    .unwrap()
+            })
+            .collect::<Vec<f64>>();
+
+        assert!(predictions[0] > 4.0);
+        assert!(predictions[0] > predictions[1]);
+        assert!(predictions[1] > predictions[2]);
+    }
+
+    #[test]
+    fn prediction_matches_onnx_reference() {
+        let mapper = LatticeSimilarityToQualityMapper::new(MODEL_PATH).unwrap();
+        let bands = (0..NUM_BANDS_SPEECH).map(|band| band as f64);
+        let fvnsim = bands
+            .clone()
+            .map(|band| 0.95 - 0.012 * band)
+            .collect::<Vec<_>>();
+        let fvnsim10 = bands
+            .clone()
+            .map(|band| 0.85 - 0.015 * band)
+            .collect::<Vec<_>>();
+        let fstdnsim = bands
+            .clone()
+            .map(|band| 0.02 + 0.003 * band)
+            .collect::<Vec<_>>();
+        let fvdegenergy = bands.map(|band| 15.0 + 1.5 * band).collect::<Vec<_>>();
+
+        let moslqo = mapper
+            .predict_quality(&fvnsim, &fvnsim10, &fstdnsim, &fvdegenergy)
+            .unwrap();
+
+        // Output of model/model.onnx for the inputs `Placeholder_<4 * band + feature>:0` above and `Placeholder_84:0` (tau) = 0.5,
+        // evaluated in f32 like `onnx_inference.py`. Summing the products of the MatMuls in f64 gives 2.409762 instead.
+        assert_abs_diff_eq!(moslqo, 2.409698247909546, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn invalid_models_and_bands_are_rejected() {
+        assert!(matches!(
+            LatticeSimilarityToQualityMapper::new("/nonexistent.onnx"),
+            Err(VisqolError::FailedToLoadModel { .. })
+        ));
+        assert_eq!(
+            LatticeSimilarityToQualityMapper::feature_index("Placeholder_12:0"),
+            Some(12)
+        );
+        assert_eq!(
+            LatticeSimilarityToQualityMapper::feature_index("Placeholder:0"),
+            Some(0)
+        );
+        assert_eq!(
+            LatticeSimilarityToQualityMapper::feature_index("input:0"),
+            None
+        );
+
+        let mapper = LatticeSimilarityToQualityMapper::new(MODEL_PATH).unwrap();
+        let features = [0.9; 32];
+        assert!(matches!(
+            mapper.predict_quality(&features, &features, &features, &features),
+            Err(VisqolError::WrongNumberOfBands {
+                required: NUM_BANDS_SPEECH,
+                found: 32
+            })
+        ));
+    }
+}
@@ -3,18 +3,19 @@
 //! # Example
 //!
 //! ```
-//! use visqol_rs::constants::{DEFAULT_WINDOW_SIZE, NUM_BANDS_SPEECH};
-//! use visqol_rs::variant::Variant;
+//! use visqol_rs::constants::DEFAULT_WINDOW_SIZE;
+//! use visqol_rs::variant::{Variant, WidebandMapping};
 //! use visqol_rs::*;
 //!
 //! let path_to_reference_file = "./test_data/clean_speech/reference_signal.wav";
 //! let path_to_degraded_file = "./test_data/clean_speech/degraded_signal.wav";
 //!
 //! let variant = Variant::Wideband {
-//!     use_unscaled_mos_mapping: true,
+//!     mapping: WidebandMapping::Polynomial {
+//!         use_unscaled_mos_mapping: true,
+//!     },
 //! };
-//! let mut visqol =
-//!     visqol_manager::VisqolManager::<NUM_BANDS_SPEECH>::new(variant, DEFAULT_WINDOW_SIZE);
+//! let mut visqol = visqol_manager::VisqolManager::new(variant, DEFAULT_WINDOW_SIZE).unwrap();
 //!
 //! let similarity_result = visqol
 //!     .run(path_to_reference_file, path_to_degraded_file)
@@ -26,39 +27,63 @@
 //! );
 //! ```
 
+pub mod ab_comparison;
 mod alignment;
-mod analysis_window;
-mod audio_signal;
+pub mod analysis_window;
+pub mod audio_signal;
+pub mod audio_source;
 pub mod audio_utils;
+pub mod bootstrap;
+pub mod channel_mode;
+pub mod clock_drift;
 mod comparison_patches_selector;
+#[cfg(any(
+    feature = "flac",
+    feature = "vorbis",
+    feature = "opus",
+    feature = "mp3",
+    feature = "aiff"
+))]
+mod compressed_audio_decoder;
 pub mod constants;
 mod convolution_2d;
 mod envelope;
 mod equivalent_rectangular_bandwidth;
 mod fast_fourier_transform;
 mod fft_manager;
-mod gammatone_filterbank;
-mod gammatone_spectrogram_builder;
+pub mod gammatone_filterbank;
+pub mod gammatone_spectrogram_builder;
 mod image_patch_creator;
+pub mod impairment_report;
+#[cfg(feature = "lattice")]
+mod lattice_similarity_to_quality_mapper;
 mod math_utils;
-mod neurogram_similiarity_index_measure;
+pub mod mel_spectrogram_builder;
+pub mod neurogram_similiarity_index_measure;
 mod patch_creator;
-mod patch_similarity_comparator;
+pub mod patch_similarity_comparator;
+pub mod quality_trace;
+pub mod resampler;
 mod rms_vad;
 mod signal_filter;
 pub mod similarity_result;
-mod similarity_to_quality_mapper;
-mod spectrogram;
-mod spectrogram_builder;
+pub mod similarity_to_quality_mapper;
+pub mod spectrogram;
+pub mod spectrogram_builder;
 mod speech_similarity_to_quality_mapper;
+pub mod stft_spectrogram_builder;
 mod support_vector_regression_model;
 mod svr_similarity_to_quality_mapper;
+pub mod time_range;
+pub mod time_warping;
 mod vad_patch_creator;
 pub mod variant;
 mod visqol;
-mod visqol_error;
+pub mod visqol_config;
+pub mod visqol_error;
 pub mod visqol_manager;
 mod wav_reader;
+pub mod worst_segments;
 mod xcorr;
 
 #[cfg(test)]
@@ -20,11 +20,12 @@ pub fn next_pow_two(input: usize) -> usize {
 /// Returns the exponential fit between 2 points
 pub fn exponential_from_fit(x: f64, a: f64, b: f64, x_0: f64) -> f64 { a + (b * (x - x_0)).exp() }
 
-/// Normalizes a slice of `i16` to a vector of `f64` values
-pub fn normalize_int16_to_double(input: &[i16]) -> Vec<f64> {
+/// Normalizes a slice of integer samples with the given `bits_per_sample` to a vector of `f64` values
+pub fn normalize_int_to_double(input: &[i32], bits_per_sample: u16) -> Vec<f64> {
+    let full_scale = ((1i64 << (bits_per_sample - 1)) - 1) as f64;
     input
         .iter()
-        .map(|x| *x as f64 / 32767.0f64)
+        .map(|x| *x as f64 / full_scale)
         .collect::<Vec<f64>>()
 }
 
@@ -48,6 +49,14 @@ mod tests {
         assert_eq!(results, expected);
     }
 
+    #[test]
+    fn test_normalize_int_to_double() {
+        assert_eq!(normalize_int_to_double(&[32767, 0], 16), vec![1.0, 0.0]);
+        assert_eq!(normalize_int_to_double(&[-127], 8), vec![-1.0]);
+        assert_eq!(normalize_int_to_double(&[8_388_607], 24), vec![1.0]);
+        assert_eq!(normalize_int_to_double(&[i32::MAX], 32), vec![1.0]);
+    }
+
     #[test]
     fn test_exponential_from_fit() {
         assert_abs_diff_eq!(
@@ -0,0 +1,125 @@
+use crate::analysis_window::AnalysisWindow;
+use crate::spectrogram::Spectrogram;
+use crate::spectrogram_builder::SpectrogramBuilder;
+use crate::stft_spectrogram_builder::StftSpectrogramBuilder;
+use crate::{audio_signal::AudioSignal, visqol_error::VisqolError};
+use ndarray::{Array2, Axis};
+
+/// Produces a frequency domain representation from a time domain signal using triangular mel filters on top of a short-time Fourier transform.
+pub struct MelSpectrogramBuilder {
+    stft_builder: StftSpectrogramBuilder,
+    /// Number of mel bands
+    pub num_bands: usize,
+    /// Lower edge of the lowest band in Hz
+    pub min_freq: f64,
+    /// Upper edge of the highest band in Hz, limited to the Nyquist frequency of the signal
+    pub max_freq: f64,
+}
+
+impl MelSpectrogramBuilder {
+    /// Creates a new mel spectrogram builder with `num_bands` bands spaced evenly on the mel scale between `min_freq` and `max_freq`.
+    pub fn new(num_bands: usize, min_freq: f64, max_freq: f64) -> Self {
+        Self {
+            stft_builder: StftSpectrogramBuilder::new(),
+            num_bands,
+            min_freq,
+            max_freq,
+        }
+    }
+
+    fn hz_to_mel(freq: f64) -> f64 { 2595.0 * (1.0 + freq / 700.0).log10() }
+
+    fn mel_to_hz(mel: f64) -> f64 { 700.0 * (10.0f64.powf(mel / 2595.0) - 1.0) }
+
+    /// Returns the weights of the triangular filters with one row per band and one column per bin, as well as the center frequencies of the bands.
+    fn make_filters(&self, bin_freqs: &[f64], max_freq: f64) -> (Array2<f64>, Vec<f64>) {
+        let min_mel = Self::hz_to_mel(self.min_freq);
+        let max_mel = Self::hz_to_mel(max_freq);
+        let mel_step = (max_mel - min_mel) / (self.num_bands + 1) as f64;
+        let edges = (0..self.num_bands + 2)
+            .map(|index| Self::mel_to_hz(min_mel + index as f64 * mel_step))
+            .collect::<Vec<f64>>();
+
+        let mut filters = Array2::<f64>::zeros((self.num_bands, bin_freqs.len()));
+        for band in 0..self.num_bands {
+            let (lower, center, upper) = (edges[band], edges[band + 1], edges[band + 2]);
+            for (bin, &freq) in bin_freqs.iter().enumerate() {
+                filters[(band, bin)] = if freq > lower && freq <= center {
+                    (freq - lower) / (center - lower)
+                } else if freq > center && freq < upper {
+                    (upper - freq) / (upper - center)
+                } else {
+                    0.0
+                };
+            }
+        }
+        (filters, edges[1..=self.num_bands].to_vec())
+    }
+}
+
+impl SpectrogramBuilder for MelSpectrogramBuilder {
+    fn build(
+        &mut self,
+        signal: &AudioSignal,
+        window: &AnalysisWindow,
+    ) -> Result<Spectrogram, VisqolError> {
+        let (magnitudes, bin_freqs) = self.stft_builder.magnitudes(signal, window)?;
+        let max_freq = self.max_freq.min(signal.sample_rate as f64 / 2.0);
+        let (filters, center_freqs) = self.make_filters(&bin_freqs, max_freq);
+
+        // Sum the power of the bins in each band, so that the bands keep the RMS scale of the bins.
+        let mut band_levels = Array2::<f64>::zeros((self.num_bands, magnitudes.ncols()));
+        for (band, weights) in filters.axis_iter(Axis(0)).enumerate() {
+            for (frame, bins) in magnitudes.axis_iter(Axis(1)).enumerate() {
+                let power = weights
+                    .iter()
+                    .zip(bins.iter())
+                    .map(|(weight, magnitude)| weight * magnitude * magnitude)
+                    .sum::<f64>();
+                band_levels[(band, frame)] = power.sqrt();
+            }
+        }
+        Ok(Spectrogram::new(band_levels, center_freqs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray_stats::QuantileExt;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn sine_peaks_in_its_band() {
+        const SAMPLE_RATE: u32 = 16000;
+        const NUM_BANDS: usize = 32;
+        const FREQ: f64 = 2000.0;
+        let samples = (0..SAMPLE_RATE)
+            .map(|n| 0.5 * (2.0 * PI * FREQ * n as f64 / SAMPLE_RATE as f64).sin())
+            .collect::<Vec<f64>>();
+        let signal = AudioSignal::new(&samples, SAMPLE_RATE);
+        let window = AnalysisWindow::new(SAMPLE_RATE, 0.25, 0.08);
+
+        let spectrogram = MelSpectrogramBuilder::new(NUM_BANDS, 50.0, 8000.0)
+            .build(&signal, &window)
+            .unwrap();
+
+        assert_eq!(spectrogram.data.nrows(), NUM_BANDS);
+        assert_eq!(spectrogram.center_freq_bands.len(), NUM_BANDS);
+        assert!(spectrogram
+            .center_freq_bands
+            .windows(2)
+            .all(|pair| pair[0] < pair[1]));
+
+        let frame = spectrogram.data.index_axis(Axis(1), 10);
+        let peak_band = frame.argmax().unwrap();
+        let closest_band = spectrogram
+            .center_freq_bands
+            .iter()
+            .map(|center| (center - FREQ).abs())
+            .collect::<ndarray::Array1<f64>>()
+            .argmin()
+            .unwrap();
+        assert_eq!(peak_band, closest_band);
+    }
+}
@@ -11,8 +11,8 @@ pub struct NeurogramSimiliarityIndexMeasure {
     intensity_range: f64,
 }
 
-#[allow(unused)]
 impl NeurogramSimiliarityIndexMeasure {
+    /// Creates a new NSIM measure for spectrograms with values in a range of `intensity_range`.
     pub fn new(intensity_range: f64) -> Self { Self { intensity_range } }
 }
 
@@ -41,6 +41,10 @@ impl PatchSimilarityResult {
             deg_patch_end_time: 0.0,
         }
     }
+
+    /// Returns whether a degraded patch was matched to the reference patch.
+    /// Reference patches without a match, e.g. because of a dropout, have an empty degraded time range.
+    pub fn is_matched(&self) -> bool { self.deg_patch_start_time != self.deg_patch_end_time }
 }
 
 impl Default for PatchSimilarityResult {
@@ -60,6 +64,8 @@ impl Default for PatchSimilarityResult {
 
 /// If implemented, this trait allows for computing a similarity score of 2 patches
 pub trait PatchSimilarityComparator {
+    /// Compares `ref_patch` to `deg_patch`. The returned result holds the mean and standard deviation of the similarity
+    /// and the degraded energy of each frequency band, as well as the overall similarity. Its time information is set by the caller.
     fn measure_patch_similarity(
         &self,
         ref_patch: &mut ImagePatch<f64>,
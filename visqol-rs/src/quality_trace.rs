@@ -0,0 +1,69 @@
+use crate::{
+    patch_similarity_comparator::PatchSimilarityResult,
+    similarity_to_quality_mapper::SimilarityToQualityMapper, visqol, visqol_error::VisqolError,
+};
+use serde::Serialize;
+
+/// MOS of a group of consecutive patches, located by the time range of the reference they cover
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct QualityPoint {
+    /// Start of the first reference patch of the group in seconds
+    pub ref_start_time: f64,
+    /// End of the last reference patch of the group in seconds
+    pub ref_end_time: f64,
+    /// Predicted MOS of the group: 1 = bad, 5 = excellent
+    pub moslqo: f64,
+}
+
+/// Maps each group of `num_patches` consecutive patches of `patch_sims` to a MOS with `mapper`, sliding the group by 1 patch at a time.
+/// If there are fewer patches than `num_patches`, all patches form a single group. Returns the error of `mapper` if it fails.
+pub fn calculate_quality_trace(
+    patch_sims: &[PatchSimilarityResult],
+    num_patches: usize,
+    frame_duration: f64,
+    mapper: &dyn SimilarityToQualityMapper,
+) -> Result<Vec<QualityPoint>, VisqolError> {
+    if patch_sims.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    patch_sims
+        .windows(num_patches.clamp(1, patch_sims.len()))
+        .map(|group| {
+            Ok(QualityPoint {
+                ref_start_time: group[0].ref_patch_start_time,
+                ref_end_time: group[group.len() - 1].ref_patch_end_time,
+                moslqo: visqol::predict_quality(group, frame_duration, mapper)?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utility::{patch, LinearMapper};
+
+    #[test]
+    fn groups_slide_over_the_patches() {
+        let patch_sims = vec![
+            patch(0.0, 0.0, vec![1.0; 2]),
+            patch(0.5, 0.5, vec![0.5; 2]),
+            patch(1.0, 1.0, vec![1.0; 2]),
+        ];
+
+        let single = calculate_quality_trace(&patch_sims, 1, 0.02, &LinearMapper).unwrap();
+        let pairs = calculate_quality_trace(&patch_sims, 2, 0.02, &LinearMapper).unwrap();
+        let all = calculate_quality_trace(&patch_sims, 10, 0.02, &LinearMapper).unwrap();
+
+        assert_eq!(single.len(), 3);
+        assert_eq!(single[1].moslqo, 3.0);
+        assert_eq!(single[2].moslqo, 5.0);
+        assert_eq!(pairs.len(), 2);
+        assert_eq!(pairs[1].ref_start_time, 0.5);
+        assert_eq!(pairs[1].ref_end_time, 1.5);
+        assert_eq!(pairs[1].moslqo, 4.0);
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].ref_end_time, 1.5);
+    }
+}
@@ -0,0 +1,190 @@
+use crate::audio_signal::AudioSignal;
+use std::f64::consts::PI;
+
+// Constants
+const NUM_ZERO_CROSSINGS: f64 = 32.0;
+const ROLLOFF: f64 = 0.945;
+const KAISER_BETA: f64 = 8.6;
+
+/// Polyphase windowed-sinc resampler converting between two integer sample rates.
+/// The ratio between the rates is reduced to `up / down` and one Kaiser-windowed sinc filter is precomputed for each of the `up` phases.
+pub struct Resampler {
+    up: usize,
+    down: usize,
+    /// Number of filter taps on either side of the interpolated position
+    half_taps: usize,
+    /// One filter per output phase, each of length `2 * half_taps`
+    filters: Vec<Vec<f64>>,
+}
+
+impl Resampler {
+    /// Creates a resampler converting from `input_rate` to `output_rate`.
+    pub fn new(input_rate: u32, output_rate: u32) -> Self {
+        let divisor = gcd(input_rate as usize, output_rate as usize);
+        let up = output_rate as usize / divisor;
+        let down = input_rate as usize / divisor;
+
+        // Cutoff relative to the input Nyquist frequency, below the lower of both Nyquist frequencies.
+        let cutoff = ROLLOFF * (up as f64 / down as f64).min(1.0);
+        let half_width = NUM_ZERO_CROSSINGS / cutoff;
+        let half_taps = half_width.ceil() as usize;
+
+        let filters = (0..up)
+            .map(|phase| {
+                let fraction = phase as f64 / up as f64;
+                (0..2 * half_taps)
+                    .map(|tap| {
+                        let x = fraction + half_taps as f64 - 1.0 - tap as f64;
+                        cutoff * sinc(cutoff * x) * kaiser(x, half_width)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            up,
+            down,
+            half_taps,
+            filters,
+        }
+    }
+
+    /// Resamples `input` and returns the resampled samples.
+    pub fn process(&self, input: &[f64]) -> Vec<f64> {
+        let output_len = (input.len() * self.up).div_ceil(self.down);
+        let mut output = Vec::with_capacity(output_len);
+
+        for n in 0..output_len {
+            let position = n * self.down;
+            let base = position / self.up;
+            let filter = &self.filters[position % self.up];
+
+            // The first tap is applied on input sample `base + 1 - half_taps`.
+            let first = base as isize + 1 - self.half_taps as isize;
+            let sum = filter
+                .iter()
+                .enumerate()
+                .filter_map(|(tap, coeff)| {
+                    let index = first + tap as isize;
+                    if index < 0 {
+                        None
+                    } else {
+                        input.get(index as usize).map(|sample| sample * coeff)
+                    }
+                })
+                .sum();
+            output.push(sum);
+        }
+        output
+    }
+}
+
+/// Returns a copy of `signal` resampled to `target_sample_rate`. If the signal already has the target sample rate, the copy is returned as-is.
+pub fn resample(signal: &AudioSignal, target_sample_rate: u32) -> AudioSignal {
+    if signal.sample_rate == target_sample_rate {
+        return signal.clone();
+    }
+    let resampler = Resampler::new(signal.sample_rate, target_sample_rate);
+    AudioSignal::new(
+        &resampler.process(
+            signal
+                .data_matrix
+                .as_slice()
+                .expect("Failed to create slice from audio signal!"),
+        ),
+        target_sample_rate,
+    )
+}
+
+/// Normalized sinc function
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Kaiser window evaluated at `x`, spanning from `-half_width` to `half_width`
+fn kaiser(x: f64, half_width: f64) -> f64 {
+    let ratio = x / half_width;
+    if ratio.abs() > 1.0 {
+        return 0.0;
+    }
+    bessel_i0(KAISER_BETA * (1.0 - ratio * ratio).sqrt()) / bessel_i0(KAISER_BETA)
+}
+
+/// Zeroth order modified bessel function of the first kind, evaluated by its power series.
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half_x = x / 2.0;
+    for k in 1..50 {
+        term *= (half_x / k as f64).powi(2);
+        sum += term;
+        if term < sum * 1e-16 {
+            break;
+        }
+    }
+    sum
+}
+
+/// Greatest common divisor of `a` and `b`
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    fn sine(frequency: f64, sample_rate: u32, num_samples: usize) -> Vec<f64> {
+        (0..num_samples)
+            .map(|n| (2.0 * PI * frequency * n as f64 / sample_rate as f64).sin())
+            .collect()
+    }
+
+    #[test]
+    fn resampled_sine_matches_analytic_sine() {
+        for &(input_rate, output_rate) in &[(48000, 16000), (16000, 48000), (44100, 48000)] {
+            let input = sine(1000.0, input_rate, input_rate as usize / 10);
+            let output = Resampler::new(input_rate, output_rate).process(&input);
+            let expected = sine(1000.0, output_rate, output.len());
+
+            assert_eq!(output.len(), output_rate as usize / 10);
+            // Skip the edges where the filter runs into the zero padding.
+            for (a, b) in output
+                .iter()
+                .zip(expected.iter())
+                .skip(200)
+                .take(output.len() - 400)
+            {
+                assert_abs_diff_eq!(a, b, epsilon = 0.001);
+            }
+        }
+    }
+
+    #[test]
+    fn content_above_target_nyquist_is_removed() {
+        let input = sine(12000.0, 48000, 4800);
+        let output = Resampler::new(48000, 16000).process(&input);
+        let max = output
+            .iter()
+            .skip(100)
+            .take(output.len() - 200)
+            .fold(0.0f64, |max, e| max.max(e.abs()));
+        assert!(max < 0.001);
+    }
+
+    #[test]
+    fn same_rate_is_identity() {
+        let signal = AudioSignal::new(&sine(440.0, 16000, 1600), 16000);
+        let resampled = resample(&signal, 16000);
+        assert_eq!(resampled.data_matrix, signal.data_matrix);
+    }
+}
@@ -1,6 +1,10 @@
 use serde::Serialize;
 
-use crate::patch_similarity_comparator::PatchSimilarityResult;
+use crate::{
+    bootstrap::MosConfidenceInterval, clock_drift::LagPoint, impairment_report::Impairment,
+    patch_similarity_comparator::PatchSimilarityResult, quality_trace::QualityPoint,
+    time_warping::WarpPoint,
+};
 
 #[derive(Debug, Serialize)]
 /// Contains information for the similarity of 2 signals
@@ -11,14 +15,41 @@ pub struct SimilarityResult {
     pub vnsim: f64,
     /// Similarity score of reference and degraded file per frequency band, ordered from lowest to highest frequency
     pub fvnsim: Vec<f64>,
+    /// Mean of the lowest 10% of patch similarities per frequency band
+    pub fvnsim10: Vec<f64>,
     /// Standard deviation of similarity per frequency band
     pub fstdnsim: Vec<f64>,
     /// Degraded energy for each frequency
     pub fvdegenergy: Vec<f64>,
     /// Frequencies for which a similarity sore was computed
     pub center_freq_bands: Vec<f64>,
-    /// Similarity data for each patch in the signal
+    /// Similarity data for each patch in the signal.
+    /// Degraded times are given in the time of the degraded signal after its global alignment, before time warping and clock drift compensation.
     pub patch_sims: Vec<PatchSimilarityResult>,
+    /// Delay of the degraded signal relative to the reference in seconds, positive if the degraded signal is late.
+    /// 0 if global alignment is disabled or found no plausible delay.
+    pub global_delay: f64,
+    /// Correlation of the signal envelopes at the global delay, from -1 to 1. `None` if global alignment is disabled.
+    pub alignment_confidence: Option<f64>,
+    /// Sound pressure level of the reference signal in dB
+    pub ref_spl_db: f64,
+    /// Sound pressure level of the degraded signal in dB, before it is aligned and scaled to the level of the reference
+    pub deg_spl_db: f64,
+    /// Times of the degraded signal matched to the reference by time warping. `None` if time warping is disabled.
+    pub warp_path: Option<Vec<WarpPoint>>,
+    /// Clock drift of the degraded signal in parts per million, positive if it falls behind the reference over time.
+    /// Estimated from `lag_curve`, `None` if fewer than 2 patches were matched.
+    pub clock_drift_ppm: Option<f64>,
+    /// Delay of the degraded signal at the start of each matched reference patch, before clock drift compensation
+    pub lag_curve: Vec<LagPoint>,
+    /// Time ranges in which audio is missing from, repeated in or inserted into the degraded signal, ordered by reference time.
+    /// Degraded times are given in the time of the degraded signal before its global alignment, time warping and clock drift compensation.
+    pub impairments: Vec<Impairment>,
+    /// MOS of each group of `quality_trace_patches` consecutive patches, ordered by reference time
+    pub quality_trace: Vec<QualityPoint>,
+    /// Confidence interval and standard error of `moslqo`, estimated by resampling `patch_sims`.
+    /// `None` if `bootstrap_iterations` is 0.
+    pub moslqo_confidence: Option<MosConfidenceInterval>,
 }
 
 impl SimilarityResult {
@@ -26,6 +57,7 @@ impl SimilarityResult {
         moslqo: f64,
         vnsim: f64,
         fnsim: Vec<f64>,
+        fvnsim10: Vec<f64>,
         fstdnsim: Vec<f64>,
         fvdegenergy: Vec<f64>,
         center_freq_bands: Vec<f64>,
@@ -35,10 +67,61 @@ impl SimilarityResult {
             moslqo,
             vnsim,
             fvnsim: fnsim,
+            fvnsim10,
             fstdnsim,
             fvdegenergy,
             center_freq_bands,
             patch_sims,
+            global_delay: 0.0,
+            alignment_confidence: None,
+            ref_spl_db: 0.0,
+            deg_spl_db: 0.0,
+            warp_path: None,
+            clock_drift_ppm: None,
+            lag_curve: Vec::new(),
+            impairments: Vec::new(),
+            quality_trace: Vec::new(),
+            moslqo_confidence: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+/// Contains the similarity of 1 channel (or channel combination) of a multichannel comparison
+pub struct ChannelSimilarityResult {
+    /// Label of the compared channel, e.g. `0`, `mid` or `mono`
+    pub channel: String,
+    #[serde(flatten)]
+    pub result: SimilarityResult,
+}
+
+#[derive(Debug, Serialize)]
+/// Contains the per-channel similarities of 2 multichannel signals and their aggregate
+pub struct MultiChannelSimilarityResult {
+    /// Lowest MOS of all compared channels, so that a single broken channel lowers the score
+    pub moslqo: f64,
+    /// Mean MOS of all compared channels
+    pub mean_moslqo: f64,
+    /// Similarity results of the individual channels
+    pub channels: Vec<ChannelSimilarityResult>,
+}
+
+impl MultiChannelSimilarityResult {
+    /// Aggregates the results of the individual `channels`.
+    pub fn new(channels: Vec<ChannelSimilarityResult>) -> Self {
+        let moslqo = channels
+            .iter()
+            .map(|channel| channel.result.moslqo)
+            .fold(f64::INFINITY, f64::min);
+        let mean_moslqo = channels
+            .iter()
+            .map(|channel| channel.result.moslqo)
+            .sum::<f64>()
+            / channels.len() as f64;
+        Self {
+            moslqo,
+            mean_moslqo,
+            channels,
         }
     }
 }
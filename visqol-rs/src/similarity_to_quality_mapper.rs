@@ -1,5 +1,16 @@
+use crate::visqol_error::VisqolError;
+
 /// Trait to provide a method for predicting a MOS based on features.
-/// Given a feature, the implementations of this trait compute a single score.
+/// Given the per-band similarity features, the implementations of this trait compute a single score.
 pub trait SimilarityToQualityMapper {
-    fn predict_quality(&self, features: &[f64]) -> f64;
+    /// Predicts the MOS from the mean similarity `fvnsim`, the mean of the lowest 10% of patch similarities `fvnsim10`,
+    /// the standard deviation of the similarity `fstdnsim` and the degraded energy `fvdegenergy` of each frequency band.
+    /// Returns an error if the mapper cannot handle the number of bands or fails to evaluate its model.
+    fn predict_quality(
+        &self,
+        fvnsim: &[f64],
+        fvnsim10: &[f64],
+        fstdnsim: &[f64],
+        fvdegenergy: &[f64],
+    ) -> Result<f64, VisqolError>;
 }
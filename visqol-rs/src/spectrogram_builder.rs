@@ -4,7 +4,9 @@ use crate::{
 };
 
 /// Given a time domain signal and an analysis window, structs implementing this trait can build a spectrogram representing the signal in the frequency domain.
+/// The rows of the spectrogram hold the amplitudes of the frequency bands in ascending order, the columns hold the frames of `window`.
 pub trait SpectrogramBuilder {
+    /// Builds the spectrogram of `signal`, advancing `window` by its overlap from frame to frame.
     fn build(
         &mut self,
         signal: &AudioSignal,
@@ -1,5 +1,6 @@
 use crate::math_utils;
 use crate::similarity_to_quality_mapper::SimilarityToQualityMapper;
+use crate::visqol_error::VisqolError;
 
 /// Maps a similarity score to a MOS using polynomial mapping.
 pub struct SpeechSimilarityToQualityMapper {
@@ -17,7 +18,13 @@ impl SpeechSimilarityToQualityMapper {
 }
 
 impl SimilarityToQualityMapper for SpeechSimilarityToQualityMapper {
-    fn predict_quality(&self, similarity_vector: &[f64]) -> f64 {
+    fn predict_quality(
+        &self,
+        similarity_vector: &[f64],
+        _fvnsim10: &[f64],
+        _fstdnsim: &[f64],
+        _fvdegenergy: &[f64],
+    ) -> Result<f64, VisqolError> {
         const FIT_PARAMETER_A: f64 = 1.155_945_5;
         const FIT_PARAMETER_B: f64 = 4.685_115_3;
         const FIT_PARAMETER_X0: f64 = 0.765_523_2;
@@ -37,6 +44,6 @@ impl SimilarityToQualityMapper for SpeechSimilarityToQualityMapper {
             1.0
         };
 
-        (mos * scale).clamp(1.0, 5.0)
+        Ok((mos * scale).clamp(1.0, 5.0))
     }
 }
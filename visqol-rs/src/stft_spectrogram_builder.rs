@@ -0,0 +1,150 @@
+use crate::analysis_window::AnalysisWindow;
+use crate::fft_manager::FftManager;
+use crate::spectrogram::Spectrogram;
+use crate::spectrogram_builder::SpectrogramBuilder;
+use crate::{audio_signal::AudioSignal, visqol_error::VisqolError};
+use ndarray::Array2;
+use num::complex::Complex64;
+use num::Zero;
+use std::f64::consts::PI;
+
+/// Produces a frequency domain representation from a time domain signal using a short-time Fourier transform with a Hann window.
+/// Frames are zero-padded to the next power of two, each row holds one frequency bin from 0 Hz up to the Nyquist frequency.
+#[derive(Default)]
+pub struct StftSpectrogramBuilder {
+    /// Fft manager for the current window size, created on the first build
+    fft_manager: Option<FftManager>,
+}
+
+impl StftSpectrogramBuilder {
+    /// Creates a new short-time Fourier transform spectrogram builder.
+    pub fn new() -> Self { Self { fft_manager: None } }
+
+    /// Computes the magnitude spectrum of each frame of `signal`.
+    /// A sinusoid yields its RMS amplitude in its bin, comparable to the band levels of the gammatone spectrogram.
+    /// Returns the magnitudes with one row per bin and the bin frequencies in Hz.
+    pub(crate) fn magnitudes(
+        &mut self,
+        signal: &AudioSignal,
+        window: &AnalysisWindow,
+    ) -> Result<(Array2<f64>, Vec<f64>), VisqolError> {
+        let time_domain_signal = &signal.data_matrix;
+        let hop_size = (window.size as f64 * window.overlap) as usize;
+
+        if time_domain_signal.len() < window.size {
+            return Err(VisqolError::TooFewSamples {
+                found: time_domain_signal.len(),
+                minimum_required: window.size,
+            });
+        }
+
+        if self
+            .fft_manager
+            .as_ref()
+            .is_none_or(|manager| manager.samples_per_channel != window.size)
+        {
+            self.fft_manager = Some(FftManager::new(window.size));
+        }
+        let fft_manager = self
+            .fft_manager
+            .as_mut()
+            .expect("Failed to create fft manager for stft spectrogram!");
+
+        let hann_window = Self::hann_window(window.size);
+        let scale = std::f64::consts::SQRT_2 / hann_window.iter().sum::<f64>();
+        let num_bins = fft_manager.fft_size / 2 + 1;
+        let num_cols = 1 + ((time_domain_signal.len() - window.size) / hop_size);
+        let mut out_matrix = Array2::<f64>::zeros((num_bins, num_cols));
+        let mut freq_channel = vec![Complex64::zero(); fft_manager.fft_size];
+
+        for (index, frame) in time_domain_signal
+            .windows(window.size)
+            .into_iter()
+            .step_by(hop_size)
+            .enumerate()
+        {
+            let mut time_channel = frame
+                .iter()
+                .zip(hann_window.iter())
+                .map(|(sample, weight)| sample * weight)
+                .collect::<Vec<f64>>();
+            fft_manager.freq_from_time_domain(&mut time_channel, &mut freq_channel);
+
+            for bin in 0..num_bins {
+                out_matrix[(bin, index)] = freq_channel[bin].norm() * scale;
+            }
+        }
+
+        let bin_width = signal.sample_rate as f64 / fft_manager.fft_size as f64;
+        let bin_freqs = (0..num_bins)
+            .map(|bin| bin as f64 * bin_width)
+            .collect::<Vec<f64>>();
+        Ok((out_matrix, bin_freqs))
+    }
+
+    /// Returns a symmetric Hann window of length `size`.
+    fn hann_window(size: usize) -> Vec<f64> {
+        if size < 2 {
+            return vec![1.0; size];
+        }
+        (0..size)
+            .map(|n| 0.5 - 0.5 * (2.0 * PI * n as f64 / (size - 1) as f64).cos())
+            .collect()
+    }
+}
+
+impl SpectrogramBuilder for StftSpectrogramBuilder {
+    fn build(
+        &mut self,
+        signal: &AudioSignal,
+        window: &AnalysisWindow,
+    ) -> Result<Spectrogram, VisqolError> {
+        let (magnitudes, bin_freqs) = self.magnitudes(signal, window)?;
+        Ok(Spectrogram::new(magnitudes, bin_freqs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+    use ndarray::Axis;
+    use ndarray_stats::QuantileExt;
+
+    #[test]
+    fn sine_peaks_in_its_bin() {
+        const SAMPLE_RATE: u32 = 16000;
+        const FREQ: f64 = 1000.0;
+        let samples = (0..SAMPLE_RATE)
+            .map(|n| 0.5 * (2.0 * PI * FREQ * n as f64 / SAMPLE_RATE as f64).sin())
+            .collect::<Vec<f64>>();
+        let signal = AudioSignal::new(&samples, SAMPLE_RATE);
+        let window = AnalysisWindow::new(SAMPLE_RATE, 0.25, 0.08);
+
+        let spectrogram = StftSpectrogramBuilder::new()
+            .build(&signal, &window)
+            .unwrap();
+
+        assert_eq!(spectrogram.data.nrows(), 1025);
+        assert_eq!(spectrogram.center_freq_bands.len(), 1025);
+        assert_eq!(spectrogram.data.ncols(), 1 + (16000 - 1280) / 320);
+
+        let frame = spectrogram.data.index_axis(Axis(1), 10);
+        let peak_bin = frame.argmax().unwrap();
+        assert_abs_diff_eq!(spectrogram.center_freq_bands[peak_bin], FREQ, epsilon = 8.0);
+        assert_abs_diff_eq!(
+            frame[peak_bin],
+            0.5 / std::f64::consts::SQRT_2,
+            epsilon = 0.01
+        );
+    }
+
+    #[test]
+    fn short_signal_is_rejected() {
+        let signal = AudioSignal::new(&[0.0; 100], 16000);
+        let window = AnalysisWindow::new(16000, 0.25, 0.08);
+        assert!(StftSpectrogramBuilder::new()
+            .build(&signal, &window)
+            .is_err());
+    }
+}
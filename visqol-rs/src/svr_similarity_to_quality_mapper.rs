@@ -1,5 +1,6 @@
 use crate::similarity_to_quality_mapper::SimilarityToQualityMapper;
 use crate::support_vector_regression_model::SupportVectorRegressionModel;
+use crate::visqol_error::VisqolError;
 
 /// Maps a a similarity score to a MOS using support vector regression.
 pub struct SvrSimilarityToQualityMapper {
@@ -16,8 +17,14 @@ impl SvrSimilarityToQualityMapper {
 }
 
 impl SimilarityToQualityMapper for SvrSimilarityToQualityMapper {
-    fn predict_quality(&self, similarity_vector: &[f64]) -> f64 {
+    fn predict_quality(
+        &self,
+        similarity_vector: &[f64],
+        _fvnsim10: &[f64],
+        _fstdnsim: &[f64],
+        _fvdegenergy: &[f64],
+    ) -> Result<f64, VisqolError> {
         let solution = self.model.predict(similarity_vector);
-        solution.clamp(1.0, 5.0)
+        Ok(solution.clamp(1.0, 5.0))
     }
 }
@@ -1,3 +1,11 @@
+use crate::audio_signal::AudioSignal;
+use crate::audio_utils;
+use crate::constants::DEFAULT_WINDOW_SIZE;
+use crate::patch_similarity_comparator::PatchSimilarityResult;
+use crate::similarity_to_quality_mapper::SimilarityToQualityMapper;
+use crate::variant::{Variant, WidebandMapping};
+use crate::visqol_error::VisqolError;
+use crate::visqol_manager::VisqolManager;
 use approx::assert_abs_diff_eq;
 use ndarray::Array2;
 use num::complex::Complex;
@@ -43,6 +51,82 @@ pub fn compare_real_vec(vec_a: &[f64], vec_b: &[f64], tolerance: f64) {
     });
 }
 
+/// Maps the mean similarity of all bands linearly from 1 to 5 and checks that all features are passed.
+pub struct LinearMapper;
+
+impl SimilarityToQualityMapper for LinearMapper {
+    fn predict_quality(
+        &self,
+        fvnsim: &[f64],
+        fvnsim10: &[f64],
+        fstdnsim: &[f64],
+        fvdegenergy: &[f64],
+    ) -> Result<f64, VisqolError> {
+        for features in [fvnsim10, fstdnsim, fvdegenergy] {
+            assert_eq!(features.len(), fvnsim.len());
+        }
+        Ok(1.0 + 4.0 * fvnsim.iter().sum::<f64>() / fvnsim.len() as f64)
+    }
+}
+
+/// Returns a 0.5 s patch at `ref_start_time` matched at `deg_start_time`, whose similarity is the mean of `freq_band_means`.
+pub fn patch(
+    ref_start_time: f64,
+    deg_start_time: f64,
+    freq_band_means: Vec<f64>,
+) -> PatchSimilarityResult {
+    let num_bands = freq_band_means.len();
+    PatchSimilarityResult {
+        similarity: freq_band_means.iter().sum::<f64>() / num_bands as f64,
+        freq_band_means,
+        freq_band_stddevs: vec![0.0; num_bands],
+        freq_band_deg_energy: vec![1.0; num_bands],
+        ref_patch_start_time: ref_start_time,
+        ref_patch_end_time: ref_start_time + 0.5,
+        deg_patch_start_time: deg_start_time,
+        deg_patch_end_time: deg_start_time + 0.5,
+    }
+}
+
+/// Returns the wideband variant with the conformant polynomial MOS mapping.
+pub fn wideband() -> Variant {
+    Variant::Wideband {
+        mapping: WidebandMapping::Polynomial {
+            use_unscaled_mos_mapping: false,
+        },
+    }
+}
+
+/// Returns a manager for the wideband variant with the default search window.
+pub fn wideband_manager() -> VisqolManager {
+    VisqolManager::new(wideband(), DEFAULT_WINDOW_SIZE).unwrap()
+}
+
+/// Returns the reference speech signal and a copy that is played 3% slower from its second second on.
+pub fn stretched_speech() -> (AudioSignal, AudioSignal) {
+    let reference =
+        audio_utils::load_as_mono("test_data/clean_speech/reference_signal_16k.wav").unwrap();
+    let samples = &reference.data_matrix;
+    let stretch_start = 16000.0;
+    let degraded = (0..(samples.len() as f64 * 1.03) as usize)
+        .map(|index| {
+            let index = index as f64;
+            let position = if index < stretch_start {
+                index
+            } else {
+                stretch_start + (index - stretch_start) / 1.03
+            };
+            let previous = position.floor() as usize;
+            let fraction = position - previous as f64;
+            match samples.get(previous + 1) {
+                Some(next) => samples[previous] * (1.0 - fraction) + next * fraction,
+                None => 0.0,
+            }
+        })
+        .collect::<Vec<f64>>();
+    (reference.clone(), AudioSignal::new(&degraded, 16000))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,149 @@
+use crate::{audio_signal::AudioSignal, visqol_error::VisqolError};
+use std::{fmt, str::FromStr};
+
+/// A position in a signal, given either in seconds or in samples at the sample rate of the signal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimePosition {
+    /// Position in seconds from the start of the signal.
+    Seconds(f64),
+    /// Position in samples from the start of the signal.
+    Samples(usize),
+}
+
+impl TimePosition {
+    /// Returns the sample index of this position in a signal sampled at `sample_rate`.
+    pub fn to_samples(&self, sample_rate: u32) -> usize {
+        match *self {
+            TimePosition::Seconds(seconds) => (seconds * sample_rate as f64).round() as usize,
+            TimePosition::Samples(samples) => samples,
+        }
+    }
+}
+
+impl FromStr for TimePosition {
+    type Err = VisqolError;
+
+    /// Parses seconds (`1.5` or `1.5s`) or samples (`24000samples`).
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let invalid = || VisqolError::InvalidTimePosition {
+            spec: spec.to_string(),
+        };
+        let spec_trimmed = spec.trim();
+
+        if let Some(samples) = spec_trimmed.strip_suffix("samples") {
+            return samples
+                .trim()
+                .parse::<usize>()
+                .map(TimePosition::Samples)
+                .map_err(|_| invalid());
+        }
+
+        let seconds = spec_trimmed.strip_suffix('s').unwrap_or(spec_trimmed);
+        seconds
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+            .map(TimePosition::Seconds)
+            .ok_or_else(invalid)
+    }
+}
+
+impl fmt::Display for TimePosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimePosition::Seconds(seconds) => write!(f, "{seconds}s"),
+            TimePosition::Samples(samples) => write!(f, "{samples}samples"),
+        }
+    }
+}
+
+/// The part of a signal that is scored. Missing bounds default to the start and end of the signal.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TimeRange {
+    /// First position that is scored.
+    pub start: Option<TimePosition>,
+    /// Position after the last scored sample.
+    pub end: Option<TimePosition>,
+}
+
+impl TimeRange {
+    /// Creates a new `TimeRange`.
+    pub fn new(start: Option<TimePosition>, end: Option<TimePosition>) -> Self {
+        Self { start, end }
+    }
+
+    /// Returns `true` if the range covers the whole signal.
+    pub fn is_full(&self) -> bool { self.start.is_none() && self.end.is_none() }
+
+    /// Returns the part of `signal` inside this range and the start of the range in seconds.
+    /// An end beyond the length of `signal` is clamped to its last sample.
+    pub fn apply(&self, signal: &AudioSignal) -> Result<(AudioSignal, f64), VisqolError> {
+        let start = self
+            .start
+            .map_or(0, |start| start.to_samples(signal.sample_rate));
+        let end = self.end.map_or(signal.len(), |end| {
+            end.to_samples(signal.sample_rate).min(signal.len())
+        });
+        if start >= end {
+            return Err(VisqolError::EmptyTimeRange {
+                start,
+                end,
+                num_samples: signal.len(),
+            });
+        }
+
+        let trimmed = AudioSignal {
+            data_matrix: signal.data_matrix.slice(ndarray::s![start..end]).to_owned(),
+            sample_rate: signal.sample_rate,
+        };
+        Ok((trimmed, start as f64 / signal.sample_rate as f64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_positions_are_parsed() {
+        assert_eq!(
+            "1.5".parse::<TimePosition>().unwrap(),
+            TimePosition::Seconds(1.5)
+        );
+        assert_eq!(
+            "2s".parse::<TimePosition>().unwrap(),
+            TimePosition::Seconds(2.0)
+        );
+        assert_eq!(
+            "24000samples".parse::<TimePosition>().unwrap(),
+            TimePosition::Samples(24000)
+        );
+        assert!("-1".parse::<TimePosition>().is_err());
+        assert!("1.5samples".parse::<TimePosition>().is_err());
+        assert!("abc".parse::<TimePosition>().is_err());
+    }
+
+    #[test]
+    fn range_is_cut_from_signal() {
+        let signal = AudioSignal::new(&[0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0], 4);
+
+        let range = TimeRange::new(
+            Some(TimePosition::Seconds(0.5)),
+            Some(TimePosition::Samples(5)),
+        );
+        let (trimmed, offset) = range.apply(&signal).unwrap();
+        assert_eq!(trimmed.data_matrix.to_vec(), vec![2.0, 3.0, 4.0]);
+        assert_eq!(offset, 0.5);
+
+        let open_end = TimeRange::new(
+            Some(TimePosition::Samples(6)),
+            Some(TimePosition::Seconds(10.0)),
+        );
+        let (trimmed, _) = open_end.apply(&signal).unwrap();
+        assert_eq!(trimmed.data_matrix.to_vec(), vec![6.0, 7.0]);
+
+        let empty = TimeRange::new(Some(TimePosition::Samples(8)), None);
+        assert!(empty.apply(&signal).is_err());
+    }
+}
@@ -0,0 +1,297 @@
+use crate::analysis_window::AnalysisWindow;
+use crate::audio_signal::AudioSignal;
+use crate::audio_utils;
+use crate::mel_spectrogram_builder::MelSpectrogramBuilder;
+use crate::spectrogram_builder::SpectrogramBuilder;
+use crate::visqol_error::VisqolError;
+use ndarray::{Array2, Axis};
+use serde::Serialize;
+
+/// Duration of the frames compared by dynamic time warping in seconds
+const FRAME_DURATION: f64 = 0.04;
+/// Hop size of the frames as a fraction of their duration
+const FRAME_OVERLAP: f64 = 0.5;
+/// Number of mel bands describing each frame
+const NUM_BANDS: usize = 24;
+/// Dynamic range of the frame features in dB, quieter bins are raised to this floor
+const DYNAMIC_RANGE_DB: f64 = 60.0;
+/// Interval between the points of the smoothed warp path in seconds
+const WARP_POINT_INTERVAL: f64 = 0.5;
+
+/// A point of a warp path, mapping a time of the reference to the matching time of the degraded signal
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct WarpPoint {
+    /// Time in the reference signal in seconds
+    pub ref_time: f64,
+    /// Matching time in the degraded signal in seconds
+    pub deg_time: f64,
+}
+
+/// Warps the timeline of `deg_signal` to follow `ref_signal`, e.g. to undo the stretching and compression of a jitter buffer.
+/// The frames of both signals are matched with dynamic time warping, allowing them to drift apart by at most `max_warp` seconds.
+/// The resulting path is smoothed to one point every half second, between which the degraded signal is resampled linearly.
+/// Returns the warped degraded signal, which has the length of the reference, and the smoothed warp path.
+pub fn warp_to_reference(
+    ref_signal: &AudioSignal,
+    deg_signal: &AudioSignal,
+    max_warp: f64,
+) -> Result<(AudioSignal, Vec<WarpPoint>), VisqolError> {
+    let window = AnalysisWindow::new(ref_signal.sample_rate, FRAME_OVERLAP, FRAME_DURATION);
+    let hop_duration = window.size as f64 * window.overlap / ref_signal.sample_rate as f64;
+
+    let deg_signal_scaled =
+        audio_utils::scale_to_match_sound_pressure_level(ref_signal, deg_signal);
+    let mut builder =
+        MelSpectrogramBuilder::new(NUM_BANDS, 50.0, ref_signal.sample_rate as f64 / 2.0);
+    let mut ref_features = builder.build(ref_signal, &window)?.data;
+    let mut deg_features = builder.build(&deg_signal_scaled, &window)?.data;
+    to_decibels(&mut ref_features, &mut deg_features);
+
+    let radius = ((max_warp / hop_duration).round() as usize).max(1);
+    let frame_path = find_warp_path(&ref_features, &deg_features, radius);
+    let warp_path = smooth_warp_path(&frame_path, hop_duration, ref_signal.get_duration());
+    Ok((
+        apply_warp_path(ref_signal, deg_signal, &warp_path),
+        warp_path,
+    ))
+}
+
+/// Converts the band levels of both spectrograms to dB, limited to `DYNAMIC_RANGE_DB` below the loudest bin of the reference.
+fn to_decibels(ref_features: &mut Array2<f64>, deg_features: &mut Array2<f64>) {
+    let peak = ref_features
+        .iter()
+        .cloned()
+        .fold(f64::MIN_POSITIVE, f64::max);
+    let floor_db = 20.0 * peak.log10() - DYNAMIC_RANGE_DB;
+    for level in ref_features.iter_mut().chain(deg_features.iter_mut()) {
+        *level = (20.0 * level.max(f64::MIN_POSITIVE).log10()).max(floor_db);
+    }
+}
+
+/// Finds the path of frame pairs with the lowest total distance by dynamic time warping.
+/// Only degraded frames within `radius` frames of the reference frame are considered. The path starts at the first frames of both signals and ends at the last reference frame.
+fn find_warp_path(
+    ref_features: &Array2<f64>,
+    deg_features: &Array2<f64>,
+    radius: usize,
+) -> Vec<(usize, usize)> {
+    let num_ref_frames = ref_features.ncols();
+    let num_deg_frames = deg_features.ncols();
+    let band = |ref_frame: usize| {
+        let first = ref_frame.saturating_sub(radius).min(num_deg_frames - 1);
+        let last = (ref_frame + radius).min(num_deg_frames - 1);
+        first..=last
+    };
+    let distance = |ref_frame: usize, deg_frame: usize| {
+        ref_features
+            .index_axis(Axis(1), ref_frame)
+            .iter()
+            .zip(deg_features.index_axis(Axis(1), deg_frame).iter())
+            .map(|(a, b)| (a - b).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    };
+
+    // Accumulated cost of the cheapest path to each frame pair, stored only for the band of each reference frame.
+    let mut cost = (0..num_ref_frames)
+        .map(|ref_frame| vec![None; band(ref_frame).count()])
+        .collect::<Vec<Vec<Option<f64>>>>();
+    let cost_at = |cost: &[Vec<Option<f64>>], ref_frame: usize, deg_frame: usize| {
+        let index = deg_frame.checked_sub(*band(ref_frame).start())?;
+        cost[ref_frame].get(index).copied().flatten()
+    };
+    for ref_frame in 0..num_ref_frames {
+        for deg_frame in band(ref_frame) {
+            let predecessor = if ref_frame == 0 && deg_frame == 0 {
+                Some(0.0)
+            } else {
+                let diagonal = match (ref_frame, deg_frame) {
+                    (0, _) | (_, 0) => None,
+                    _ => cost_at(&cost, ref_frame - 1, deg_frame - 1),
+                };
+                let vertical = ref_frame
+                    .checked_sub(1)
+                    .and_then(|row| cost_at(&cost, row, deg_frame));
+                let horizontal = deg_frame
+                    .checked_sub(1)
+                    .and_then(|col| cost_at(&cost, ref_frame, col));
+                [diagonal, vertical, horizontal]
+                    .iter()
+                    .flatten()
+                    .cloned()
+                    .reduce(f64::min)
+            };
+            let index = deg_frame - band(ref_frame).start();
+            cost[ref_frame][index] =
+                predecessor.map(|predecessor: f64| predecessor + distance(ref_frame, deg_frame));
+        }
+    }
+
+    // Trace the path back from the cheapest pair of the last reference frame.
+    let mut ref_frame = num_ref_frames - 1;
+    let mut deg_frame = band(ref_frame)
+        .filter_map(|deg_frame| Some((deg_frame, cost_at(&cost, ref_frame, deg_frame)?)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or(num_deg_frames - 1, |(deg_frame, _)| deg_frame);
+    let mut path = vec![(ref_frame, deg_frame)];
+    while ref_frame > 0 || deg_frame > 0 {
+        let candidates = [
+            (ref_frame.checked_sub(1), deg_frame.checked_sub(1)),
+            (ref_frame.checked_sub(1), Some(deg_frame)),
+            (Some(ref_frame), deg_frame.checked_sub(1)),
+        ];
+        let (previous_ref, previous_deg) = candidates
+            .iter()
+            .filter_map(|&(row, col)| {
+                let (row, col) = (row?, col?);
+                Some((row, col, cost_at(&cost, row, col)?))
+            })
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+            .map_or((0, 0), |(row, col, _)| (row, col));
+        ref_frame = previous_ref;
+        deg_frame = previous_deg;
+        path.push((ref_frame, deg_frame));
+    }
+    path.reverse();
+    path
+}
+
+/// Reduces a frame path to one point every `WARP_POINT_INTERVAL` seconds, using the median offset between the frames in each interval.
+/// The degraded times of the points never decrease.
+fn smooth_warp_path(
+    frame_path: &[(usize, usize)],
+    hop_duration: f64,
+    ref_duration: f64,
+) -> Vec<WarpPoint> {
+    let frames_per_interval = ((WARP_POINT_INTERVAL / hop_duration).round() as usize).max(1);
+    let mut warp_path = Vec::<WarpPoint>::new();
+    for interval in
+        frame_path.chunk_by(|a, b| a.0 / frames_per_interval == b.0 / frames_per_interval)
+    {
+        let mut offsets = interval
+            .iter()
+            .map(|&(ref_frame, deg_frame)| deg_frame as f64 - ref_frame as f64)
+            .collect::<Vec<f64>>();
+        offsets.sort_by(|a, b| a.total_cmp(b));
+        let median_offset = offsets[offsets.len() / 2] * hop_duration;
+
+        let first_frame = interval[0].0 as f64;
+        let last_frame = interval[interval.len() - 1].0 as f64;
+        let ref_time = ((first_frame + last_frame) / 2.0 * hop_duration).min(ref_duration);
+        let min_deg_time = warp_path.last().map_or(f64::MIN, |point| point.deg_time);
+        warp_path.push(WarpPoint {
+            ref_time,
+            deg_time: (ref_time + median_offset).max(min_deg_time),
+        });
+    }
+    warp_path
+}
+
+/// Returns the time of the degraded signal that `warp_path` matches to `ref_time`, e.g. to locate a time of the warped signal in the degraded signal.
+/// The warp path is interpolated linearly between its points and continues with a constant offset before the first and after the last point.
+pub fn to_degraded_time(warp_path: &[WarpPoint], ref_time: f64) -> f64 {
+    match warp_path {
+        [] => ref_time,
+        [point] => ref_time + point.deg_time - point.ref_time,
+        _ => {
+            let segment = warp_path
+                .partition_point(|point| point.ref_time <= ref_time)
+                .saturating_sub(1)
+                .min(warp_path.len() - 2);
+            let (start, end) = (warp_path[segment], warp_path[segment + 1]);
+            if ref_time < start.ref_time {
+                ref_time + start.deg_time - start.ref_time
+            } else if ref_time > end.ref_time {
+                ref_time + end.deg_time - end.ref_time
+            } else {
+                let fraction = (ref_time - start.ref_time) / (end.ref_time - start.ref_time);
+                start.deg_time + fraction * (end.deg_time - start.deg_time)
+            }
+        }
+    }
+}
+
+/// Resamples `deg_signal` so that each time of the reference is taken from the matching time of the degraded signal, see `to_degraded_time`.
+/// Samples beyond the end of the degraded signal are zero.
+fn apply_warp_path(
+    ref_signal: &AudioSignal,
+    deg_signal: &AudioSignal,
+    warp_path: &[WarpPoint],
+) -> AudioSignal {
+    let sample_rate = ref_signal.sample_rate as f64;
+    let deg_samples = &deg_signal.data_matrix;
+    let warped = (0..ref_signal.len())
+        .map(|index| {
+            let deg_time = to_degraded_time(warp_path, index as f64 / sample_rate);
+            let position = deg_time * sample_rate;
+            if position < 0.0 || position >= (deg_samples.len() - 1) as f64 {
+                return 0.0;
+            }
+            let previous = position.floor() as usize;
+            let fraction = position - previous as f64;
+            deg_samples[previous] * (1.0 - fraction) + deg_samples[previous + 1] * fraction
+        })
+        .collect::<Vec<f64>>();
+    AudioSignal::new(&warped, ref_signal.sample_rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utility::stretched_speech;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn identical_signals_are_not_warped() {
+        let (reference, _) = stretched_speech();
+        let (warped, warp_path) = warp_to_reference(&reference, &reference, 0.5).unwrap();
+
+        assert_eq!(warped.len(), reference.len());
+        for point in &warp_path {
+            assert_abs_diff_eq!(point.ref_time, point.deg_time, epsilon = 1e-9);
+        }
+        assert_abs_diff_eq!(
+            warped.data_matrix[5000],
+            reference.data_matrix[5000],
+            epsilon = 1e-9
+        );
+    }
+
+    #[test]
+    fn warp_path_follows_stretched_signal() {
+        let (reference, degraded) = stretched_speech();
+        let (warped, warp_path) = warp_to_reference(&reference, &degraded, 0.5).unwrap();
+
+        assert_eq!(warped.len(), reference.len());
+        assert!(warp_path
+            .windows(2)
+            .all(|pair| pair[0].deg_time <= pair[1].deg_time));
+        for point in &warp_path {
+            let expected = if point.ref_time < 1.0 {
+                point.ref_time
+            } else {
+                1.0 + (point.ref_time - 1.0) * 1.03
+            };
+            assert_abs_diff_eq!(point.deg_time, expected, epsilon = 0.06);
+        }
+    }
+
+    #[test]
+    fn warp_path_is_interpolated() {
+        let warp_path = [
+            WarpPoint {
+                ref_time: 1.0,
+                deg_time: 1.0,
+            },
+            WarpPoint {
+                ref_time: 2.0,
+                deg_time: 2.2,
+            },
+        ];
+
+        assert_abs_diff_eq!(to_degraded_time(&warp_path, 0.5), 0.5);
+        assert_abs_diff_eq!(to_degraded_time(&warp_path, 1.5), 1.6);
+        assert_abs_diff_eq!(to_degraded_time(&warp_path, 3.0), 3.2);
+        assert_abs_diff_eq!(to_degraded_time(&[], 3.0), 3.0);
+    }
+}
@@ -146,6 +146,34 @@ mod tests {
         assert_eq!(K_CA01_01_VAD_RES_COUNT, res.len());
     }
 
+    #[test]
+    fn vad_is_independent_of_bit_depth() {
+        const K_START_SAMPLE: usize = 14;
+        const K_TOTAL_SAMPLE: usize = 115200;
+        const K_FRAME_LEN: usize = 480;
+
+        let vad = VadPatchCreator::new(20);
+        let voice_activity = |file_path: &str| {
+            let signal = load_as_mono(file_path).unwrap();
+            vad.get_voice_activity(
+                signal.data_matrix.as_slice().unwrap(),
+                K_START_SAMPLE,
+                K_TOTAL_SAMPLE,
+                K_FRAME_LEN,
+            )
+        };
+
+        let expected = voice_activity("test_data/clean_speech/CA01_01.wav");
+        assert_eq!(
+            voice_activity("test_data/clean_speech/CA01_01_8bits.wav"),
+            expected
+        );
+        assert_eq!(
+            voice_activity("test_data/clean_speech/CA01_01_32bits.wav"),
+            expected
+        );
+    }
+
     #[test]
     fn patch_indices() {
         const _K_MINIMUM_FREQ: f64 = 50.0;
@@ -154,8 +182,11 @@ mod tests {
         let expected_patches = vec![9, 29, 49, 69, 89];
         let ref_signal = load_as_mono("test_data/clean_speech/CA01_01.wav").unwrap();
 
-        let mut spectrogram_builder: GammatoneSpectrogramBuilder<NUM_BANDS_SPEECH> =
-            GammatoneSpectrogramBuilder::new(GammatoneFilterbank::<NUM_BANDS_SPEECH>::new(50.0));
+        let mut spectrogram_builder = GammatoneSpectrogramBuilder::new(GammatoneFilterbank::new(
+            NUM_BANDS_SPEECH,
+            50.0,
+            8000.0,
+        ));
         let window = AnalysisWindow::new(ref_signal.sample_rate, 0.25, 0.08);
 
         let spectrogram = spectrogram_builder.build(&ref_signal, &window).unwrap();
@@ -1,4 +1,116 @@
+use crate::constants::{
+    MINIMUM_FREQ, NARROWBAND_MAX_FREQ, NARROWBAND_MIN_FREQ, NUM_BANDS_AUDIO, NUM_BANDS_NARROWBAND,
+    NUM_BANDS_SPEECH, NUM_BANDS_SUPER_WIDEBAND, PATCH_SIZE_AUDIO, PATCH_SIZE_SPEECH,
+    SAMPLE_RATE_AUDIO, SAMPLE_RATE_NARROWBAND, SAMPLE_RATE_SPEECH, SAMPLE_RATE_SUPER_WIDEBAND,
+    SPEECH_MODE_MAX_FREQ, SUPER_WIDEBAND_MAX_FREQ,
+};
+
 pub enum Variant {
-    Fullband { model_path: String },
-    Wideband { use_unscaled_mos_mapping: bool },
+    Fullband {
+        model_path: String,
+    },
+    Wideband {
+        mapping: WidebandMapping,
+    },
+    /// Wideband speech mode that maps similarity to MOS with the ONNX lattice model of ViSQOL v3.3 in `model_path`.
+    #[cfg(feature = "lattice")]
+    WidebandLattice {
+        model_path: String,
+    },
+    /// Narrowband speech mode for telephony signals at 8 kHz, e.g. G.711 or AMR-NB, compared in the telephone band from 300 Hz to 3.4 kHz.
+    /// Similarity is mapped to MOS with the exponential fit of wideband mode. A perfect similarity maps to 5.0, or to ~4.x if `use_unscaled_mos_mapping` is set.
+    /// The fit was not calibrated against narrowband listening tests, so the MOS can rank conditions but is not comparable to narrowband MOS scales such as P.862.
+    Narrowband {
+        use_unscaled_mos_mapping: bool,
+    },
+    /// Super-wideband speech mode for signals at 32 kHz, e.g. Opus or EVS in super-wideband mode, compared up to 16 kHz.
+    /// Its bands above 8 kHz continue the spacing of wideband mode, so that missing high frequencies lower the similarity.
+    /// Similarity is mapped to MOS with the exponential fit of wideband mode. A perfect similarity maps to 5.0, or to ~4.x if `use_unscaled_mos_mapping` is set.
+    /// The fit was calibrated for 21 bands up to 8 kHz and not against super-wideband listening tests, so the MOS can rank conditions but is not comparable to wideband MOS.
+    SuperWideband {
+        use_unscaled_mos_mapping: bool,
+    },
+}
+
+/// Determines how the similarity of wideband speech signals is mapped to a MOS.
+pub enum WidebandMapping {
+    /// Exponential fit of the mean similarity. A perfect similarity maps to 5.0, or to ~4.x if `use_unscaled_mos_mapping` is set.
+    Polynomial { use_unscaled_mos_mapping: bool },
+    /// Support vector regression of the per-band similarity with the libsvm model in `model_path`, e.g. the TCD-VoIP model in `model/`.
+    Svr { model_path: String },
+}
+
+impl Variant {
+    /// Returns the sample rate at which signals are evaluated in this variant. Input signals are resampled to this rate.
+    pub fn sample_rate(&self) -> u32 {
+        match self {
+            Variant::Fullband { .. } => SAMPLE_RATE_AUDIO,
+            Variant::Wideband { .. } => SAMPLE_RATE_SPEECH,
+            #[cfg(feature = "lattice")]
+            Variant::WidebandLattice { .. } => SAMPLE_RATE_SPEECH,
+            Variant::Narrowband { .. } => SAMPLE_RATE_NARROWBAND,
+            Variant::SuperWideband { .. } => SAMPLE_RATE_SUPER_WIDEBAND,
+        }
+    }
+
+    /// Returns `true` if this variant evaluates speech. Speech mode only compares patches with voice activity and limits the frequency bands to the speech band of the variant.
+    pub fn is_speech_mode(&self) -> bool { !matches!(self, Variant::Fullband { .. }) }
+
+    /// Returns the default number of gammatone frequency bands of this variant.
+    pub fn num_bands(&self) -> usize {
+        match self {
+            Variant::Fullband { .. } => NUM_BANDS_AUDIO,
+            Variant::Wideband { .. } => NUM_BANDS_SPEECH,
+            #[cfg(feature = "lattice")]
+            Variant::WidebandLattice { .. } => NUM_BANDS_SPEECH,
+            Variant::Narrowband { .. } => NUM_BANDS_NARROWBAND,
+            Variant::SuperWideband { .. } => NUM_BANDS_SUPER_WIDEBAND,
+        }
+    }
+
+    /// Returns the number of gammatone frequency bands that the similarity to quality model of this variant was trained with,
+    /// or `None` if the model accepts any number of bands.
+    pub fn required_num_bands(&self) -> Option<usize> {
+        match self {
+            #[cfg(feature = "lattice")]
+            Variant::WidebandLattice { .. } => Some(NUM_BANDS_SPEECH),
+            Variant::Fullband { .. }
+            | Variant::Wideband { .. }
+            | Variant::Narrowband { .. }
+            | Variant::SuperWideband { .. } => None,
+        }
+    }
+
+    /// Returns the default number of spectrogram frames per patch of this variant.
+    pub fn patch_size(&self) -> usize {
+        if self.is_speech_mode() {
+            PATCH_SIZE_AUDIO
+        } else {
+            PATCH_SIZE_SPEECH
+        }
+    }
+
+    /// Returns the default lower bound of the gammatone center frequencies of this variant in Hz.
+    pub fn min_freq(&self) -> f64 {
+        match self {
+            Variant::Narrowband { .. } => NARROWBAND_MIN_FREQ,
+            Variant::Fullband { .. } | Variant::Wideband { .. } | Variant::SuperWideband { .. } => {
+                MINIMUM_FREQ
+            }
+            #[cfg(feature = "lattice")]
+            Variant::WidebandLattice { .. } => MINIMUM_FREQ,
+        }
+    }
+
+    /// Returns the default upper bound of the gammatone center frequencies of this variant in Hz.
+    pub fn max_freq(&self) -> f64 {
+        match self {
+            Variant::Fullband { .. } => self.sample_rate() as f64 / 2.0,
+            Variant::Wideband { .. } => SPEECH_MODE_MAX_FREQ,
+            #[cfg(feature = "lattice")]
+            Variant::WidebandLattice { .. } => SPEECH_MODE_MAX_FREQ,
+            Variant::Narrowband { .. } => NARROWBAND_MAX_FREQ,
+            Variant::SuperWideband { .. } => SUPER_WIDEBAND_MAX_FREQ,
+        }
+    }
 }
@@ -1,38 +1,50 @@
 use crate::{
-    analysis_window::AnalysisWindow, audio_signal::AudioSignal, audio_utils,
-    comparison_patches_selector::ComparisonPatchesSelector,
-    gammatone_spectrogram_builder::GammatoneSpectrogramBuilder, patch_creator::PatchCreator,
-    patch_similarity_comparator::PatchSimilarityResult, similarity_result::SimilarityResult,
-    similarity_to_quality_mapper::SimilarityToQualityMapper,
-    spectrogram_builder::SpectrogramBuilder,
+    analysis_window::AnalysisWindow, audio_signal::AudioSignal, audio_utils, bootstrap,
+    comparison_patches_selector::ComparisonPatchesSelector, patch_creator::PatchCreator,
+    patch_similarity_comparator::PatchSimilarityResult, quality_trace,
+    similarity_result::SimilarityResult, similarity_to_quality_mapper::SimilarityToQualityMapper,
+    spectrogram_builder::SpectrogramBuilder, visqol_config::VisqolConfig,
+    visqol_error::VisqolError,
 };
 use ndarray::Array1;
 use std::error::Error;
 
 /// Perform a comparison on two audio signals. Their similarity is calculated
 /// and converted to a quality score using the given similarity to quality
-/// mapper.
-pub fn calculate_similarity<const NUM_BANDS: usize>(
+/// mapper. The search window, noise floors and realignment are taken from
+/// `config`. The degraded signal is compared to the reference patches at
+/// `ref_patch_indices` if given, or to the patches of `patch_creator`.
+pub fn calculate_similarity(
     ref_signal: &mut AudioSignal,
     deg_signal: &mut AudioSignal,
-    spect_builder: &mut GammatoneSpectrogramBuilder<NUM_BANDS>,
+    spect_builder: &mut dyn SpectrogramBuilder,
     window: &AnalysisWindow,
     patch_creator: &dyn PatchCreator,
+    ref_patch_indices: Option<&[usize]>,
     selector: &ComparisonPatchesSelector,
     sim_to_qual_mapper: &dyn SimilarityToQualityMapper,
-    search_window: usize,
+    config: &VisqolConfig,
 ) -> Result<SimilarityResult, Box<dyn Error>> {
+    let noise_floor = &config.noise_floor;
     /////////////////// Stage 1: Preprocessing ///////////////////
     let deg_signal_scaled =
         audio_utils::scale_to_match_sound_pressure_level(ref_signal, deg_signal);
     let mut ref_spectrogram = spect_builder.build(ref_signal, window)?;
     let mut deg_spectrogram = spect_builder.build(&deg_signal_scaled, window)?;
 
-    audio_utils::prepare_spectrograms_for_comparison(&mut ref_spectrogram, &mut deg_spectrogram);
+    audio_utils::prepare_spectrograms_for_comparison(
+        &mut ref_spectrogram,
+        &mut deg_spectrogram,
+        noise_floor,
+    );
 
     /////////////// Stage 2: Feature selection and similarity measure ////////////
-    let mut ref_patch_indices =
-        patch_creator.create_ref_patch_indices(&ref_spectrogram.data, ref_signal, window)?;
+    let mut ref_patch_indices = match ref_patch_indices {
+        Some(ref_patch_indices) => ref_patch_indices.to_vec(),
+        None => {
+            patch_creator.create_ref_patch_indices(&ref_spectrogram.data, ref_signal, window)?
+        }
+    };
 
     let frame_duration = calculate_frame_duration(
         window.size as f64 * window.overlap,
@@ -47,48 +59,106 @@ pub fn calculate_similarity<const NUM_BANDS: usize>(
         &mut ref_patch_indices,
         &deg_spectrogram.data,
         frame_duration,
-        search_window as i32,
+        config.search_window as i32,
     )?;
     // Realign the patches in time domain subsignals that start at the coarse
     // patch times.
-
-    let realign_result = selector.finely_align_and_recreate_patches(
-        &mut sim_match_info,
-        ref_signal,
-        &deg_signal_scaled,
-        spect_builder,
-        window,
-    )?;
-    sim_match_info = realign_result;
+    if !config.disable_realignment {
+        let realign_result = selector.finely_align_and_recreate_patches(
+            &mut sim_match_info,
+            ref_signal,
+            &deg_signal_scaled,
+            spect_builder,
+            window,
+            noise_floor,
+        )?;
+        sim_match_info = realign_result;
+    }
 
     let fvnsim = calc_per_patch_mean_freq_band_means(&sim_match_info);
+    let fvnsim10 = calc_per_patch_lowest_decile_freq_band_means(&sim_match_info);
     let fstdnsim = calc_per_patch_mean_freq_band_std_devs(&sim_match_info, frame_duration);
     let fvdegenergy = calc_per_patch_mean_freq_band_degraded_energy(&sim_match_info);
 
-    let mut moslqo = predict_mos(
-        fvnsim
-            .as_slice()
-            .expect("failed to convert fvnsim to slice"),
-        sim_to_qual_mapper,
-    );
-
     let vnsim = fvnsim.mean().expect("Failed to compute nsim mean");
-
-    moslqo = alter_for_similarity_extremes(vnsim, moslqo);
-    Ok(SimilarityResult::new(
+    let moslqo = map_to_quality(
+        sim_to_qual_mapper,
+        vnsim,
+        &fvnsim,
+        &fvnsim10,
+        &fstdnsim,
+        &fvdegenergy,
+    )?;
+    let mut result = SimilarityResult::new(
         moslqo,
         vnsim,
         fvnsim.to_vec(),
+        fvnsim10.to_vec(),
         fstdnsim.to_vec(),
         fvdegenergy.to_vec(),
         ref_spectrogram.center_freq_bands,
         sim_match_info,
-    ))
+    );
+    result.quality_trace = quality_trace::calculate_quality_trace(
+        &result.patch_sims,
+        config.quality_trace_patches,
+        frame_duration,
+        sim_to_qual_mapper,
+    )?;
+    result.moslqo_confidence = bootstrap::estimate_confidence_interval(
+        &result.patch_sims,
+        frame_duration,
+        sim_to_qual_mapper,
+        config.bootstrap_iterations,
+        config.bootstrap_confidence_level,
+        config.bootstrap_seed,
+    )?;
+    result.ref_spl_db = audio_utils::calculate_sound_pressure_level(ref_signal);
+    result.deg_spl_db = audio_utils::calculate_sound_pressure_level(deg_signal);
+    Ok(result)
+}
+
+/// Predicts the MOS of the patches in `sim_match_info` from their per-band similarity features.
+pub(crate) fn predict_quality(
+    sim_match_info: &[PatchSimilarityResult],
+    frame_duration: f64,
+    sim_to_qual_mapper: &dyn SimilarityToQualityMapper,
+) -> Result<f64, VisqolError> {
+    let fvnsim = calc_per_patch_mean_freq_band_means(sim_match_info);
+    map_to_quality(
+        sim_to_qual_mapper,
+        fvnsim.mean().expect("Failed to compute nsim mean"),
+        &fvnsim,
+        &calc_per_patch_lowest_decile_freq_band_means(sim_match_info),
+        &calc_per_patch_mean_freq_band_std_devs(sim_match_info, frame_duration),
+        &calc_per_patch_mean_freq_band_degraded_energy(sim_match_info),
+    )
 }
 
-/// Computes prediction with the given `SimilarityToQualityMapper`
-fn predict_mos(fvnsim: &[f64], mapper: &dyn SimilarityToQualityMapper) -> f64 {
-    mapper.predict_quality(fvnsim)
+/// Maps the per-band similarity features to a MOS, clamped to 1.0 if the mean similarity `vnsim` is too low
+fn map_to_quality(
+    sim_to_qual_mapper: &dyn SimilarityToQualityMapper,
+    vnsim: f64,
+    fvnsim: &Array1<f64>,
+    fvnsim10: &Array1<f64>,
+    fstdnsim: &Array1<f64>,
+    fvdegenergy: &Array1<f64>,
+) -> Result<f64, VisqolError> {
+    let moslqo = sim_to_qual_mapper.predict_quality(
+        fvnsim
+            .as_slice()
+            .expect("failed to convert fvnsim to slice"),
+        fvnsim10
+            .as_slice()
+            .expect("failed to convert fvnsim10 to slice"),
+        fstdnsim
+            .as_slice()
+            .expect("failed to convert fstdnsim to slice"),
+        fvdegenergy
+            .as_slice()
+            .expect("failed to convert fvdegenergy to slice"),
+    )?;
+    Ok(alter_for_similarity_extremes(vnsim, moslqo))
 }
 
 /// Calculates the mean across all patch similarity per frequency band
@@ -102,6 +172,23 @@ fn calc_per_patch_mean_freq_band_means(sim_match_info: &[PatchSimilarityResult])
     fvnsim / sim_match_info.len() as f64
 }
 
+/// Calculates the mean of the lowest 10% of patch similarities per frequency band
+fn calc_per_patch_lowest_decile_freq_band_means(
+    sim_match_info: &[PatchSimilarityResult],
+) -> Array1<f64> {
+    let num_lowest = (sim_match_info.len() as f64 * 0.1).ceil().max(1.0) as usize;
+    let mut fvnsim10 = Array1::<f64>::zeros(sim_match_info[0].freq_band_means.len());
+    for (index, band) in fvnsim10.iter_mut().enumerate() {
+        let mut band_means = sim_match_info
+            .iter()
+            .map(|patch| patch.freq_band_means[index])
+            .collect::<Vec<f64>>();
+        band_means.sort_by(|a, b| a.total_cmp(b));
+        *band = band_means[..num_lowest].iter().sum::<f64>() / num_lowest as f64;
+    }
+    fvnsim10
+}
+
 /// Calculates the energy of the degraded patch across all patch similarity per frequency band
 fn calc_per_patch_mean_freq_band_degraded_energy(
     sim_match_info: &[PatchSimilarityResult],
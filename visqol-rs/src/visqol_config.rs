@@ -0,0 +1,415 @@
+use crate::{
+    analysis_window::AnalysisWindow,
+    audio_utils::NoiseFloor,
+    constants::{
+        DEFAULT_BOOTSTRAP_CONFIDENCE_LEVEL, DEFAULT_BOOTSTRAP_SEED, DEFAULT_MAX_TIME_WARP,
+        DEFAULT_QUALITY_TRACE_PATCHES, DEFAULT_WINDOW_SIZE, OVERLAP, SAMPLE_RATE_AUDIO,
+        WINDOW_DURATION,
+    },
+    variant::Variant,
+    visqol_error::VisqolError,
+};
+use serde::{Deserialize, Serialize};
+
+/// Parameters of the stages of ViSQOL. The defaults are conformant with the reference implementation.
+/// Parameters that are `None` are determined by the variant, see `resolve`.
+/// Missing fields are set to their defaults when deserializing, e.g. from a TOML or JSON file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VisqolConfig {
+    /// Number of patches left and right of a reference patch that are searched for the best matching degraded patch
+    pub search_window: usize,
+    /// Duration of the analysis window of the spectrograms in seconds
+    pub window_duration: f64,
+    /// Hop size of the analysis window as a fraction of its duration
+    pub overlap: f64,
+    /// Number of spectrogram frames per patch
+    pub patch_size: Option<usize>,
+    /// Number of gammatone frequency bands
+    pub num_bands: Option<usize>,
+    /// Lower bound of the gammatone center frequencies in Hz
+    pub min_freq: Option<f64>,
+    /// Upper bound of the gammatone center frequencies in Hz
+    pub max_freq: Option<f64>,
+    /// Noise floors applied to the spectrograms before they are compared
+    pub noise_floor: NoiseFloor,
+    /// Skips aligning the whole degraded signal to the reference, e.g. for files that are already sample-aligned
+    pub disable_global_alignment: bool,
+    /// Skips realigning each pair of matched patches in the time domain
+    pub disable_realignment: bool,
+    /// Warps the timeline of the degraded signal to follow the reference after the global alignment, e.g. to undo jitter buffer adaptations
+    pub enable_time_warping: bool,
+    /// Largest drift in seconds between the signals that time warping follows
+    pub max_time_warp: f64,
+    /// Resamples the degraded signal to remove the clock drift estimated from the matched patches and scores it again, if the estimate is plausible, see `clock_drift::is_compensable`
+    pub compensate_clock_drift: bool,
+    /// Number of consecutive patches that are mapped to each MOS of the quality trace
+    pub quality_trace_patches: usize,
+    /// Number of resampled patch sets used to estimate the confidence interval of the MOS, 0 to skip the estimation
+    pub bootstrap_iterations: usize,
+    /// Probability that the confidence interval of the MOS contains the MOS
+    pub bootstrap_confidence_level: f64,
+    /// Seed of the random patch resampling, so that confidence intervals are reproducible
+    pub bootstrap_seed: u64,
+}
+
+impl Default for VisqolConfig {
+    fn default() -> Self {
+        Self {
+            search_window: DEFAULT_WINDOW_SIZE,
+            window_duration: WINDOW_DURATION,
+            overlap: OVERLAP,
+            patch_size: None,
+            num_bands: None,
+            min_freq: None,
+            max_freq: None,
+            noise_floor: NoiseFloor::default(),
+            disable_global_alignment: false,
+            disable_realignment: false,
+            enable_time_warping: false,
+            max_time_warp: DEFAULT_MAX_TIME_WARP,
+            compensate_clock_drift: false,
+            quality_trace_patches: DEFAULT_QUALITY_TRACE_PATCHES,
+            bootstrap_iterations: 0,
+            bootstrap_confidence_level: DEFAULT_BOOTSTRAP_CONFIDENCE_LEVEL,
+            bootstrap_seed: DEFAULT_BOOTSTRAP_SEED,
+        }
+    }
+}
+
+impl VisqolConfig {
+    /// Returns a builder that starts from the default configuration.
+    pub fn builder() -> VisqolConfigBuilder { VisqolConfigBuilder::default() }
+
+    /// Returns a copy of this configuration in which all parameters left to the variant are set to the defaults of `variant`.
+    pub fn resolve(&self, variant: &Variant) -> Self {
+        Self {
+            patch_size: Some(self.patch_size.unwrap_or_else(|| variant.patch_size())),
+            num_bands: Some(self.num_bands.unwrap_or_else(|| variant.num_bands())),
+            min_freq: Some(self.min_freq.unwrap_or_else(|| variant.min_freq())),
+            max_freq: Some(self.max_freq.unwrap_or_else(|| variant.max_freq())),
+            ..self.clone()
+        }
+    }
+
+    /// Checks that all parameters are in their valid ranges.
+    /// The analysis window must span at least one sample at the highest sample rate of any variant, see `validate_for`.
+    pub fn validate(&self) -> Result<(), VisqolError> {
+        if !self.window_duration.is_finite() || self.window_duration <= 0.0 {
+            return invalid("window_duration", "greater than 0");
+        }
+        if !(self.overlap > 0.0 && self.overlap <= 1.0) {
+            return invalid("overlap", "greater than 0 and at most 1");
+        }
+        if self.patch_size == Some(0) {
+            return invalid("patch_size", "greater than 0");
+        }
+        if self.num_bands == Some(0) {
+            return invalid("num_bands", "greater than 0");
+        }
+        if self
+            .min_freq
+            .is_some_and(|min_freq| !min_freq.is_finite() || min_freq < 0.0)
+        {
+            return invalid("min_freq", "at least 0");
+        }
+        if self
+            .max_freq
+            .is_some_and(|max_freq| max_freq.is_nan() || max_freq <= self.min_freq.unwrap_or(0.0))
+        {
+            return invalid("max_freq", "greater than min_freq");
+        }
+        if !(self.noise_floor.absolute_db.is_finite()
+            && self.noise_floor.relative_to_peak_db.is_finite())
+        {
+            return invalid("noise_floor", "finite");
+        }
+        if !self.max_time_warp.is_finite() || self.max_time_warp <= 0.0 {
+            return invalid("max_time_warp", "greater than 0");
+        }
+        if self.quality_trace_patches == 0 {
+            return invalid("quality_trace_patches", "greater than 0");
+        }
+        if !(self.bootstrap_confidence_level > 0.0 && self.bootstrap_confidence_level < 1.0) {
+            return invalid(
+                "bootstrap_confidence_level",
+                "greater than 0 and less than 1",
+            );
+        }
+        self.validate_window(SAMPLE_RATE_AUDIO)
+    }
+
+    /// Checks that all parameters are in their valid ranges and that the analysis window and its hop span at least one sample at the sample rate of `variant`.
+    /// If the similarity to quality model of `variant` requires a fixed number of bands, `num_bands` must be unset or match it.
+    pub fn validate_for(&self, variant: &Variant) -> Result<(), VisqolError> {
+        self.validate()?;
+        if let Some(required_num_bands) = variant.required_num_bands() {
+            if self
+                .num_bands
+                .is_some_and(|num_bands| num_bands != required_num_bands)
+            {
+                return invalid(
+                    "num_bands",
+                    "the number of bands the similarity to quality model was trained with, 21 for the lattice model",
+                );
+            }
+        }
+        self.validate_window(variant.sample_rate())
+    }
+
+    /// Checks that the analysis window and its hop span at least one sample at `sample_rate`.
+    fn validate_window(&self, sample_rate: u32) -> Result<(), VisqolError> {
+        let window = AnalysisWindow::new(sample_rate, self.overlap, self.window_duration);
+        if window.size == 0 {
+            return invalid("window_duration", "at least 1 sample long");
+        }
+        if ((window.size as f64 * window.overlap) as usize) == 0 {
+            return invalid("overlap", "a hop of at least 1 sample");
+        }
+        Ok(())
+    }
+}
+
+/// Returns the error for a `parameter` that does not meet `requirement`.
+fn invalid(parameter: &'static str, requirement: &'static str) -> Result<(), VisqolError> {
+    Err(VisqolError::InvalidConfig {
+        parameter,
+        requirement,
+    })
+}
+
+/// Builds a `VisqolConfig`, overriding the defaults one parameter at a time.
+#[derive(Debug, Clone, Default)]
+pub struct VisqolConfigBuilder {
+    config: VisqolConfig,
+}
+
+impl VisqolConfigBuilder {
+    /// Sets the number of patches that are searched left and right of each reference patch.
+    pub fn search_window(mut self, search_window: usize) -> Self {
+        self.config.search_window = search_window;
+        self
+    }
+
+    /// Sets the duration of the analysis window in seconds.
+    pub fn window_duration(mut self, window_duration: f64) -> Self {
+        self.config.window_duration = window_duration;
+        self
+    }
+
+    /// Sets the hop size of the analysis window as a fraction of its duration.
+    pub fn overlap(mut self, overlap: f64) -> Self {
+        self.config.overlap = overlap;
+        self
+    }
+
+    /// Sets the number of spectrogram frames per patch.
+    pub fn patch_size(mut self, patch_size: usize) -> Self {
+        self.config.patch_size = Some(patch_size);
+        self
+    }
+
+    /// Sets the number of gammatone frequency bands.
+    pub fn num_bands(mut self, num_bands: usize) -> Self {
+        self.config.num_bands = Some(num_bands);
+        self
+    }
+
+    /// Sets the lower bound of the gammatone center frequencies in Hz.
+    pub fn min_freq(mut self, min_freq: f64) -> Self {
+        self.config.min_freq = Some(min_freq);
+        self
+    }
+
+    /// Sets the upper bound of the gammatone center frequencies in Hz.
+    pub fn max_freq(mut self, max_freq: f64) -> Self {
+        self.config.max_freq = Some(max_freq);
+        self
+    }
+
+    /// Sets the lowest level of any spectrogram bin in dB.
+    pub fn noise_floor_absolute_db(mut self, absolute_db: f64) -> Self {
+        self.config.noise_floor.absolute_db = absolute_db;
+        self
+    }
+
+    /// Sets the distance in dB below the loudest bin of a frame, below which bins are raised.
+    pub fn noise_floor_relative_to_peak_db(mut self, relative_to_peak_db: f64) -> Self {
+        self.config.noise_floor.relative_to_peak_db = relative_to_peak_db;
+        self
+    }
+
+    /// Skips aligning the whole degraded signal to the reference if `disable` is set.
+    pub fn disable_global_alignment(mut self, disable: bool) -> Self {
+        self.config.disable_global_alignment = disable;
+        self
+    }
+
+    /// Skips realigning each pair of matched patches if `disable` is set.
+    pub fn disable_realignment(mut self, disable: bool) -> Self {
+        self.config.disable_realignment = disable;
+        self
+    }
+
+    /// Warps the timeline of the degraded signal to follow the reference if `enable` is set.
+    pub fn enable_time_warping(mut self, enable: bool) -> Self {
+        self.config.enable_time_warping = enable;
+        self
+    }
+
+    /// Sets the largest drift in seconds between the signals that time warping follows.
+    pub fn max_time_warp(mut self, max_time_warp: f64) -> Self {
+        self.config.max_time_warp = max_time_warp;
+        self
+    }
+
+    /// Removes the estimated clock drift from the degraded signal and scores it again if `compensate` is set.
+    pub fn compensate_clock_drift(mut self, compensate: bool) -> Self {
+        self.config.compensate_clock_drift = compensate;
+        self
+    }
+
+    /// Sets the number of consecutive patches that are mapped to each MOS of the quality trace.
+    pub fn quality_trace_patches(mut self, quality_trace_patches: usize) -> Self {
+        self.config.quality_trace_patches = quality_trace_patches;
+        self
+    }
+
+    /// Sets the number of resampled patch sets used to estimate the confidence interval of the MOS, 0 to skip the estimation.
+    pub fn bootstrap_iterations(mut self, bootstrap_iterations: usize) -> Self {
+        self.config.bootstrap_iterations = bootstrap_iterations;
+        self
+    }
+
+    /// Sets the probability that the confidence interval of the MOS contains the MOS.
+    pub fn bootstrap_confidence_level(mut self, bootstrap_confidence_level: f64) -> Self {
+        self.config.bootstrap_confidence_level = bootstrap_confidence_level;
+        self
+    }
+
+    /// Sets the seed of the random patch resampling.
+    pub fn bootstrap_seed(mut self, bootstrap_seed: u64) -> Self {
+        self.config.bootstrap_seed = bootstrap_seed;
+        self
+    }
+
+    /// Validates and returns the configuration.
+    pub fn build(self) -> Result<VisqolConfig, VisqolError> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constants::{
+            MINIMUM_FREQ, NARROWBAND_MAX_FREQ, NARROWBAND_MIN_FREQ, NUM_BANDS_NARROWBAND,
+            NUM_BANDS_SPEECH, PATCH_SIZE_AUDIO, SPEECH_MODE_MAX_FREQ,
+        },
+        test_utility::wideband,
+    };
+
+    #[test]
+    fn builder_overrides_defaults() {
+        let config = VisqolConfig::builder()
+            .overlap(0.5)
+            .num_bands(32)
+            .noise_floor_absolute_db(-60.0)
+            .build()
+            .unwrap();
+
+        assert_eq!(config.overlap, 0.5);
+        assert_eq!(config.num_bands, Some(32));
+        assert_eq!(config.noise_floor.absolute_db, -60.0);
+        assert_eq!(config.window_duration, WINDOW_DURATION);
+        assert_eq!(config.noise_floor.relative_to_peak_db, 45.0);
+    }
+
+    #[test]
+    fn invalid_parameters_are_rejected() {
+        assert!(VisqolConfig::builder().overlap(0.0).build().is_err());
+        assert!(VisqolConfig::builder().patch_size(0).build().is_err());
+        assert!(VisqolConfig::builder().max_time_warp(0.0).build().is_err());
+        assert!(VisqolConfig::builder()
+            .quality_trace_patches(0)
+            .build()
+            .is_err());
+        assert!(VisqolConfig::builder()
+            .bootstrap_confidence_level(1.0)
+            .build()
+            .is_err());
+        assert!(VisqolConfig::builder()
+            .min_freq(1000.0)
+            .max_freq(500.0)
+            .build()
+            .is_err());
+        assert!(VisqolConfig::builder()
+            .window_duration(1e-6)
+            .build()
+            .is_err());
+        assert!(VisqolConfig::builder()
+            .window_duration(0.001)
+            .overlap(0.01)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn window_is_validated_at_the_variant_sample_rate() {
+        // 48 samples at 48 kHz, but less than 1 sample at 8 kHz.
+        let config = VisqolConfig::builder()
+            .window_duration(0.001)
+            .overlap(0.1)
+            .build()
+            .unwrap();
+
+        assert!(config
+            .validate_for(&Variant::Fullband {
+                model_path: String::new(),
+            })
+            .is_ok());
+        assert!(config
+            .validate_for(&Variant::Narrowband {
+                use_unscaled_mos_mapping: false,
+            })
+            .is_err());
+    }
+
+    #[cfg(feature = "lattice")]
+    #[test]
+    fn bands_are_validated_against_the_lattice_model() {
+        let lattice = Variant::WidebandLattice {
+            model_path: String::new(),
+        };
+        let config = VisqolConfig::builder().num_bands(32).build().unwrap();
+
+        assert!(config.validate_for(&wideband()).is_ok());
+        assert!(config.validate_for(&lattice).is_err());
+        assert!(VisqolConfig::builder()
+            .num_bands(NUM_BANDS_SPEECH)
+            .build()
+            .unwrap()
+            .validate_for(&lattice)
+            .is_ok());
+        assert!(VisqolConfig::default().validate_for(&lattice).is_ok());
+    }
+
+    #[test]
+    fn resolve_fills_in_variant_defaults() {
+        let config = VisqolConfig::default().resolve(&wideband());
+
+        assert_eq!(config.patch_size, Some(PATCH_SIZE_AUDIO));
+        assert_eq!(config.num_bands, Some(NUM_BANDS_SPEECH));
+        assert_eq!(config.max_freq, Some(SPEECH_MODE_MAX_FREQ));
+        assert_eq!(config.min_freq, Some(MINIMUM_FREQ));
+
+        let narrowband = VisqolConfig::default().resolve(&Variant::Narrowband {
+            use_unscaled_mos_mapping: false,
+        });
+        assert_eq!(narrowband.num_bands, Some(NUM_BANDS_NARROWBAND));
+        assert_eq!(narrowband.min_freq, Some(NARROWBAND_MIN_FREQ));
+        assert_eq!(narrowband.max_freq, Some(NARROWBAND_MAX_FREQ));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::audio_source::AudioFormat;
 use thiserror::Error;
 
 #[non_exhaustive]
@@ -31,6 +32,69 @@ pub enum VisqolError {
     #[error("Failed to compute VAD!")]
     FailedToComputeVad,
 
-    #[error("Visqol input files must be quantized to 16 bit. Found {bits_per_sample:?}!")]
+    #[error("Interleaved signal of {num_samples:?} samples cannot be split into {num_channels:?} channels!")]
+    InvalidInterleavedLength {
+        num_samples: usize,
+        num_channels: usize,
+    },
+
+    #[error("Channel counts differ! Reference signal has {reference:?} channels, degraded signal has {degraded:?} channels")]
+    DifferentChannelCounts { reference: usize, degraded: usize },
+
+    #[error("Mid/side comparison requires stereo signals. Found {num_channels:?} channels!")]
+    MidSideRequiresStereo { num_channels: usize },
+
+    #[error("Channel {channel:?} does not exist in a signal with {num_channels:?} channels!")]
+    ChannelOutOfRange { channel: usize, num_channels: usize },
+
+    #[error("No channels were selected for comparison!")]
+    NoChannelsSelected,
+
+    #[error("Visqol input files must be quantized to 8, 16, 24 or 32 bit integer or 32 bit float. Found {bits_per_sample:?} bit!")]
     InvalidBitsPerSample { bits_per_sample: u16 },
+
+    #[error("Could not detect the audio format of {file_path:?}!")]
+    UnknownAudioFormat { file_path: String },
+
+    #[error("Decoding {format:?} files requires the `{feature}` feature of visqol-rs!")]
+    AudioFormatNotEnabled {
+        format: AudioFormat,
+        feature: &'static str,
+    },
+
+    #[error("Invalid raw format {spec:?}! Expected <s16le|f32le>:<sample rate>[:<channels>], e.g. s16le:48000:2")]
+    InvalidRawFormat { spec: String },
+
+    #[error("Invalid time position {spec:?}! Expected seconds (e.g. 1.5 or 1.5s) or samples (e.g. 24000samples)")]
+    InvalidTimePosition { spec: String },
+
+    #[error("Time range from sample {start:?} to {end:?} is empty in a signal with {num_samples:?} samples!")]
+    EmptyTimeRange {
+        start: usize,
+        end: usize,
+        num_samples: usize,
+    },
+
+    #[error("Degraded signals A and B were compared to different reference patches, starting with patch {patch_index:?}!")]
+    DifferentPatchSets { patch_index: usize },
+
+    #[error("Invalid configuration! `{parameter}` must be {requirement}")]
+    InvalidConfig {
+        parameter: &'static str,
+        requirement: &'static str,
+    },
+
+    #[error("Failed to decode audio stream: {reason}!")]
+    InvalidAudioStream { reason: &'static str },
+
+    #[error("Failed to load the model {model_path:?}: {reason}!")]
+    FailedToLoadModel { model_path: String, reason: String },
+
+    #[error(
+        "The similarity to quality mapper requires {required:?} frequency bands, found {found:?}!"
+    )]
+    WrongNumberOfBands { required: usize, found: usize },
+
+    #[error("Failed to predict the quality: {reason}!")]
+    FailedToPredictQuality { reason: String },
 }
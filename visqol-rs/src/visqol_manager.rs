@@ -1,65 +1,157 @@
+use num_traits::Float;
 use std::error::Error;
 
+#[cfg(feature = "lattice")]
+use crate::lattice_similarity_to_quality_mapper::LatticeSimilarityToQualityMapper;
+
 use crate::{
+    ab_comparison::{self, AbComparisonResult},
     alignment,
     analysis_window::AnalysisWindow,
     audio_signal::AudioSignal,
     audio_utils,
+    channel_mode::ChannelMode,
+    clock_drift,
     comparison_patches_selector::ComparisonPatchesSelector,
-    constants::{self, PATCH_SIZE_AUDIO, PATCH_SIZE_SPEECH},
+    constants::{DEFAULT_AB_BOOTSTRAP_ITERATIONS, DURATION_MISMATCH_TOLERANCE},
     gammatone_filterbank::GammatoneFilterbank,
     gammatone_spectrogram_builder::GammatoneSpectrogramBuilder,
     image_patch_creator::ImagePatchCreator,
+    impairment_report,
     neurogram_similiarity_index_measure::NeurogramSimiliarityIndexMeasure,
     patch_creator::PatchCreator,
-    similarity_result::SimilarityResult,
+    patch_similarity_comparator::PatchSimilarityComparator,
+    resampler,
+    similarity_result::{ChannelSimilarityResult, MultiChannelSimilarityResult, SimilarityResult},
     similarity_to_quality_mapper::SimilarityToQualityMapper,
+    spectrogram_builder::SpectrogramBuilder,
     speech_similarity_to_quality_mapper::SpeechSimilarityToQualityMapper,
     svr_similarity_to_quality_mapper::SvrSimilarityToQualityMapper,
+    time_range::TimeRange,
+    time_warping,
     vad_patch_creator::VadPatchCreator,
-    variant::Variant,
+    variant::{Variant, WidebandMapping},
     visqol,
+    visqol_config::VisqolConfig,
     visqol_error::VisqolError,
 };
 
 /// Configures and executes audio evaluation using ViSQOL.
-pub struct VisqolManager<const NUM_BANDS: usize> {
-    search_window: usize,
+pub struct VisqolManager {
+    variant: Variant,
+    config: VisqolConfig,
+    sample_rate: u32,
+    ref_range: TimeRange,
+    deg_range: TimeRange,
     patch_creator: Box<dyn PatchCreator>,
     patch_selector: ComparisonPatchesSelector,
-    spectrogram_builder: GammatoneSpectrogramBuilder<NUM_BANDS>,
-    sim_to_quality_mapper: Box<dyn SimilarityToQualityMapper>,
+    spectrogram_builder: Box<dyn SpectrogramBuilder + Send>,
+    sim_to_quality_mapper: Box<dyn SimilarityToQualityMapper + Send>,
 }
 
-impl<const NUM_BANDS: usize> VisqolManager<NUM_BANDS> {
+impl VisqolManager {
     /// Creates a new instance of with the desired configurations.
-    pub fn new(variant: Variant, window_size: usize) -> Self {
-        let patch_creator: Box<dyn PatchCreator>;
-        let sim_to_quality_mapper: Box<dyn SimilarityToQualityMapper>;
-        match variant {
-            Variant::Wideband {
-                use_unscaled_mos_mapping,
-            } => {
-                patch_creator = Box::new(VadPatchCreator::new(PATCH_SIZE_AUDIO));
-                sim_to_quality_mapper = Box::new(SpeechSimilarityToQualityMapper::new(
+    /// Returns an error if the similarity to quality model of `variant` cannot be loaded.
+    pub fn new(variant: Variant, window_size: usize) -> Result<Self, VisqolError> {
+        let sim_to_quality_mapper = Self::variant_mapper(&variant)?;
+        Self::with_mapper(variant, window_size, sim_to_quality_mapper)
+    }
+
+    /// Creates a new instance that runs the stages of ViSQOL with the parameters in `config`.
+    /// Parameters that `config` leaves to the variant are set to the defaults of `variant`.
+    /// Returns an error if `config` is invalid or the similarity to quality model of `variant` cannot be loaded.
+    pub fn from_config(variant: Variant, config: &VisqolConfig) -> Result<Self, VisqolError> {
+        config.validate_for(&variant)?;
+        let sim_to_quality_mapper = Self::variant_mapper(&variant)?;
+        Ok(Self::with_config_and_mapper(
+            variant,
+            config,
+            sim_to_quality_mapper,
+        ))
+    }
+
+    /// Creates a new instance that maps similarity to MOS with `sim_to_quality_mapper` instead of the mapper of `variant`.
+    /// The variant still determines the sample rate and how patches are created.
+    /// Returns an error if the default configuration with `window_size` is invalid for `variant`, see `VisqolConfig::validate_for`.
+    pub fn with_mapper(
+        variant: Variant,
+        window_size: usize,
+        sim_to_quality_mapper: Box<dyn SimilarityToQualityMapper + Send>,
+    ) -> Result<Self, VisqolError> {
+        let config = VisqolConfig {
+            search_window: window_size,
+            ..VisqolConfig::default()
+        };
+        config.validate_for(&variant)?;
+        Ok(Self::with_config_and_mapper(
+            variant,
+            &config,
+            sim_to_quality_mapper,
+        ))
+    }
+
+    /// Returns the configuration in effect, with all parameters of the variant filled in.
+    pub fn config(&self) -> &VisqolConfig { &self.config }
+
+    /// Returns the similarity to quality mapper of `variant`.
+    fn variant_mapper(
+        variant: &Variant,
+    ) -> Result<Box<dyn SimilarityToQualityMapper + Send>, VisqolError> {
+        Ok(match variant {
+            Variant::Wideband { mapping } => match mapping {
+                WidebandMapping::Polynomial {
+                    use_unscaled_mos_mapping,
+                } => Box::new(SpeechSimilarityToQualityMapper::new(
                     !use_unscaled_mos_mapping,
-                ));
+                )),
+                WidebandMapping::Svr { model_path } => {
+                    Box::new(SvrSimilarityToQualityMapper::new(model_path))
+                }
+            },
+            #[cfg(feature = "lattice")]
+            Variant::WidebandLattice { model_path } => {
+                Box::new(LatticeSimilarityToQualityMapper::new(model_path)?)
             }
             Variant::Fullband { model_path } => {
-                patch_creator = Box::new(ImagePatchCreator::new(PATCH_SIZE_SPEECH));
-                sim_to_quality_mapper = Box::new(SvrSimilarityToQualityMapper::new(&model_path));
+                Box::new(SvrSimilarityToQualityMapper::new(model_path))
             }
-        }
+            Variant::Narrowband {
+                use_unscaled_mos_mapping,
+            }
+            | Variant::SuperWideband {
+                use_unscaled_mos_mapping,
+            } => Box::new(SpeechSimilarityToQualityMapper::new(
+                !use_unscaled_mos_mapping,
+            )),
+        })
+    }
 
-        let spectrogram_builder = GammatoneSpectrogramBuilder::<NUM_BANDS>::new(
-            GammatoneFilterbank::new(constants::MINIMUM_FREQ),
-        );
+    /// Creates a new instance with `config` resolved for `variant` that maps similarity to MOS with `sim_to_quality_mapper`.
+    fn with_config_and_mapper(
+        variant: Variant,
+        config: &VisqolConfig,
+        sim_to_quality_mapper: Box<dyn SimilarityToQualityMapper + Send>,
+    ) -> Self {
+        let config = config.resolve(&variant);
+        let sample_rate = variant.sample_rate();
+        let patch_size = config.patch_size.unwrap_or_else(|| variant.patch_size());
+        let patch_creator: Box<dyn PatchCreator> = if variant.is_speech_mode() {
+            Box::new(VadPatchCreator::new(patch_size))
+        } else {
+            Box::new(ImagePatchCreator::new(patch_size))
+        };
+
+        let spectrogram_builder = Self::gammatone_builder(&config, &variant);
 
         let patch_selector =
-            ComparisonPatchesSelector::new(NeurogramSimiliarityIndexMeasure::default());
+            ComparisonPatchesSelector::new(Box::new(NeurogramSimiliarityIndexMeasure::default()));
 
         Self {
-            search_window: window_size,
+            variant,
+            config,
+            sample_rate,
+            ref_range: TimeRange::default(),
+            deg_range: TimeRange::default(),
             patch_creator,
             patch_selector,
             spectrogram_builder,
@@ -67,18 +159,298 @@ impl<const NUM_BANDS: usize> VisqolManager<NUM_BANDS> {
         }
     }
 
+    /// Restricts scoring to `ref_range` of the reference signal and `deg_range` of the degraded signal.
+    /// The ranges are cut from the signals before alignment, at their original sample rate.
+    /// Patch times in the results stay relative to the start of the original signals.
+    pub fn set_time_ranges(&mut self, ref_range: TimeRange, deg_range: TimeRange) {
+        self.ref_range = ref_range;
+        self.deg_range = deg_range;
+    }
+
+    /// Scores pairs of reference and degraded patches with `sim_comparator` instead of the default NSIM.
+    /// The patch search and fine realignment stay the same.
+    pub fn set_patch_similarity_comparator(
+        &mut self,
+        sim_comparator: Box<dyn PatchSimilarityComparator + Send>,
+    ) {
+        self.patch_selector = ComparisonPatchesSelector::new(sim_comparator);
+    }
+
+    /// Builds gammatone spectrograms with `num_bands` bands between `min_freq` and `max_freq` instead of the default bands of the variant.
+    /// The maximum frequency is limited to the Nyquist frequency of the variant.
+    /// Returns an error and keeps the current bands if the bands are invalid for the variant, see `VisqolConfig::validate_for`.
+    pub fn set_frequency_bands(
+        &mut self,
+        num_bands: usize,
+        min_freq: f64,
+        max_freq: f64,
+    ) -> Result<(), VisqolError> {
+        let config = VisqolConfig {
+            num_bands: Some(num_bands),
+            min_freq: Some(min_freq),
+            max_freq: Some(max_freq),
+            ..self.config.clone()
+        };
+        config.validate_for(&self.variant)?;
+        self.config = config;
+        self.spectrogram_builder = Box::new(GammatoneSpectrogramBuilder::new(
+            GammatoneFilterbank::new(num_bands, min_freq, max_freq),
+        ));
+        Ok(())
+    }
+
+    /// Returns a gammatone spectrogram builder with the bands of `config`, falling back to the bands of `variant`.
+    fn gammatone_builder(
+        config: &VisqolConfig,
+        variant: &Variant,
+    ) -> Box<dyn SpectrogramBuilder + Send> {
+        Box::new(GammatoneSpectrogramBuilder::new(GammatoneFilterbank::new(
+            config.num_bands.unwrap_or_else(|| variant.num_bands()),
+            config.min_freq.unwrap_or_else(|| variant.min_freq()),
+            config.max_freq.unwrap_or_else(|| variant.max_freq()),
+        )))
+    }
+
+    /// Builds spectrograms with `spectrogram_builder` instead of the gammatone filterbank of the variant.
+    /// Alignment, patch creation and the similarity measure stay the same.
+    pub fn set_spectrogram_builder(
+        &mut self,
+        spectrogram_builder: Box<dyn SpectrogramBuilder + Send>,
+    ) {
+        self.spectrogram_builder = spectrogram_builder;
+    }
+
     /// Loads the audio store in `ref_signal_path` and `deg_signal_path` and computes its MOS.
+    /// Both signals are resampled to the sample rate of the configured variant first.
     pub fn run(
         &mut self,
         ref_signal_path: &str,
         deg_signal_path: &str,
     ) -> Result<SimilarityResult, Box<dyn Error>> {
-        let mut ref_signal = audio_utils::load_as_mono(ref_signal_path)?;
-        let mut deg_signal = audio_utils::load_as_mono(deg_signal_path)?;
+        let ref_signal = audio_utils::load_as_mono(ref_signal_path)?;
+        let deg_signal = audio_utils::load_as_mono(deg_signal_path)?;
+
+        self.score_signals(ref_signal, deg_signal)
+    }
+
+    /// Loads the audio stored in `ref_signal_path` and `deg_signal_path` and computes the MOS of their channels as selected by `channel_mode`.
+    pub fn run_channels(
+        &mut self,
+        ref_signal_path: &str,
+        deg_signal_path: &str,
+        channel_mode: &ChannelMode,
+    ) -> Result<MultiChannelSimilarityResult, Box<dyn Error>> {
+        let ref_channels = audio_utils::load_channels(ref_signal_path)?;
+        let deg_channels = audio_utils::load_channels(deg_signal_path)?;
+
+        self.score_channels(&ref_channels, &deg_channels, channel_mode)
+    }
+
+    /// Computes the MOS of the channels in `ref_channels` and `deg_channels` as selected by `channel_mode`.
+    /// Each comparison is scored like a pair of mono signals passed to `score_signals`.
+    pub fn score_channels(
+        &mut self,
+        ref_channels: &[AudioSignal],
+        deg_channels: &[AudioSignal],
+        channel_mode: &ChannelMode,
+    ) -> Result<MultiChannelSimilarityResult, Box<dyn Error>> {
+        let mut channel_results = Vec::new();
+        for (channel, ref_signal, deg_signal) in
+            channel_mode.pair_channels(ref_channels, deg_channels)?
+        {
+            channel_results.push(ChannelSimilarityResult {
+                channel,
+                result: self.score_signals(ref_signal, deg_signal)?,
+            });
+        }
+        Ok(MultiChannelSimilarityResult::new(channel_results))
+    }
+
+    /// Loads the audio stored in `ref_signal_path`, `deg_a_signal_path` and `deg_b_signal_path` and compares the quality of both degraded signals.
+    pub fn run_ab(
+        &mut self,
+        ref_signal_path: &str,
+        deg_a_signal_path: &str,
+        deg_b_signal_path: &str,
+    ) -> Result<AbComparisonResult, Box<dyn Error>> {
+        let ref_signal = audio_utils::load_as_mono(ref_signal_path)?;
+        let deg_a_signal = audio_utils::load_as_mono(deg_a_signal_path)?;
+        let deg_b_signal = audio_utils::load_as_mono(deg_b_signal_path)?;
+
+        self.score_ab_signals(ref_signal, deg_a_signal, deg_b_signal)
+    }
+
+    /// Scores the mono signals `deg_a_signal` and `deg_b_signal` against the same patches of `ref_signal` and tests whether their MOS differ.
+    /// The reference patches are selected once and both degraded signals are compared to them. Instead of a confidence interval of each MOS,
+    /// the patches are resampled in pairs `bootstrap_iterations` times, or 1000 times if the bootstrap is disabled in the configuration.
+    /// Returns an error if A or B is too short to be compared to all reference patches, since their MOS could not be paired.
+    pub fn score_ab_signals(
+        &mut self,
+        ref_signal: AudioSignal,
+        deg_a_signal: AudioSignal,
+        deg_b_signal: AudioSignal,
+    ) -> Result<AbComparisonResult, Box<dyn Error>> {
+        let ref_patch_indices = self.select_ref_patches(ref_signal.clone())?;
+        let bootstrap_iterations = std::mem::replace(&mut self.config.bootstrap_iterations, 0);
+        let results = self
+            .score_signals_at(ref_signal.clone(), deg_a_signal, Some(&ref_patch_indices))
+            .and_then(|result_a| {
+                let result_b =
+                    self.score_signals_at(ref_signal, deg_b_signal, Some(&ref_patch_indices))?;
+                Ok((result_a, result_b))
+            });
+        self.config.bootstrap_iterations = bootstrap_iterations;
+        let (result_a, result_b) = results?;
+
+        let iterations = match self.config.bootstrap_iterations {
+            0 => DEFAULT_AB_BOOTSTRAP_ITERATIONS,
+            iterations => iterations,
+        };
+        let window = AnalysisWindow::new(
+            self.sample_rate,
+            self.config.overlap,
+            self.config.window_duration,
+        );
+        let frame_duration = window.size as f64 * window.overlap / self.sample_rate as f64;
+        let test = ab_comparison::paired_bootstrap(
+            &result_a.patch_sims,
+            &result_b.patch_sims,
+            frame_duration,
+            self.sim_to_quality_mapper.as_ref(),
+            iterations,
+            self.config.bootstrap_confidence_level,
+            self.config.bootstrap_seed,
+        )?
+        .ok_or(VisqolError::SignalsTooDifferent)?;
+        Ok(AbComparisonResult {
+            moslqo_difference: result_a.moslqo - result_b.moslqo,
+            lower: test.lower,
+            upper: test.upper,
+            confidence_level: self.config.bootstrap_confidence_level,
+            p_value: test.p_value,
+            iterations,
+            result_a,
+            result_b,
+        })
+    }
+
+    /// Computes the MOS of the mono signals `reference` and `degraded`, both sampled at `sample_rate`.
+    /// The samples are expected to be scaled from -1.0 to 1.0. Both signals are resampled to the sample rate of the configured variant first.
+    pub fn score_samples<T: Float>(
+        &mut self,
+        reference: &[T],
+        degraded: &[T],
+        sample_rate: u32,
+    ) -> Result<SimilarityResult, Box<dyn Error>> {
+        let ref_signal = AudioSignal::new(&Self::to_double(reference), sample_rate);
+        let deg_signal = AudioSignal::new(&Self::to_double(degraded), sample_rate);
+
+        self.score_signals(ref_signal, deg_signal)
+    }
+
+    /// Computes the MOS of the interleaved signals `reference` and `degraded` with `num_channels` channels each.
+    /// The channels are summed and normalized to 1 mono channel, just like files passed to `run`.
+    pub fn score_interleaved<T: Float>(
+        &mut self,
+        reference: &[T],
+        degraded: &[T],
+        num_channels: usize,
+        sample_rate: u32,
+    ) -> Result<SimilarityResult, Box<dyn Error>> {
+        for signal in [reference, degraded] {
+            if num_channels == 0 || !signal.len().is_multiple_of(num_channels) {
+                return Err(Box::new(VisqolError::InvalidInterleavedLength {
+                    num_samples: signal.len(),
+                    num_channels,
+                }));
+            }
+        }
+
+        let ref_signal = AudioSignal {
+            data_matrix: audio_utils::downmix_interleaved(
+                &Self::to_double(reference),
+                num_channels,
+            ),
+            sample_rate,
+        };
+        let deg_signal = AudioSignal {
+            data_matrix: audio_utils::downmix_interleaved(&Self::to_double(degraded), num_channels),
+            sample_rate,
+        };
+
+        self.score_signals(ref_signal, deg_signal)
+    }
+
+    /// Computes the MOS of the already loaded mono signals `ref_signal` and `deg_signal`.
+    /// Both signals are cut to the configured time ranges, resampled to the sample rate of the configured variant and validated just like in `run`.
+    pub fn score_signals(
+        &mut self,
+        ref_signal: AudioSignal,
+        deg_signal: AudioSignal,
+    ) -> Result<SimilarityResult, Box<dyn Error>> {
+        self.score_signals_at(ref_signal, deg_signal, None)
+    }
+
+    /// Returns the indices of the reference patches that `score_signals` compares degraded signals to, after cutting and resampling `ref_signal`.
+    fn select_ref_patches(
+        &mut self,
+        ref_signal: AudioSignal,
+    ) -> Result<Vec<usize>, Box<dyn Error>> {
+        let (ref_signal, _) = Self::cut(ref_signal, &self.ref_range)?;
+        let ref_signal = self.resample(ref_signal);
+        let window = AnalysisWindow::new(
+            ref_signal.sample_rate,
+            self.config.overlap,
+            self.config.window_duration,
+        );
+        let ref_spectrogram = self.spectrogram_builder.build(&ref_signal, &window)?;
+        Ok(self.patch_creator.create_ref_patch_indices(
+            &ref_spectrogram.data,
+            &ref_signal,
+            &window,
+        )?)
+    }
+
+    /// Computes the MOS like `score_signals`, comparing `deg_signal` to the reference patches at `ref_patch_indices` if given.
+    fn score_signals_at(
+        &mut self,
+        ref_signal: AudioSignal,
+        deg_signal: AudioSignal,
+        ref_patch_indices: Option<&[usize]>,
+    ) -> Result<SimilarityResult, Box<dyn Error>> {
+        let (ref_signal, ref_offset) = Self::cut(ref_signal, &self.ref_range)?;
+        let (deg_signal, deg_offset) = Self::cut(deg_signal, &self.deg_range)?;
+        let mut ref_signal = self.resample(ref_signal);
+        let mut deg_signal = self.resample(deg_signal);
 
         Self::validate_input_audio(&ref_signal, &deg_signal)?;
 
-        self.compute_results(&mut ref_signal, &mut deg_signal)
+        let mut result =
+            self.compute_results_at(&mut ref_signal, &mut deg_signal, ref_patch_indices)?;
+        for patch_sim in &mut result.patch_sims {
+            patch_sim.ref_patch_start_time += ref_offset;
+            patch_sim.ref_patch_end_time += ref_offset;
+            patch_sim.deg_patch_start_time += deg_offset;
+            patch_sim.deg_patch_end_time += deg_offset;
+        }
+        for point in result.warp_path.iter_mut().flatten() {
+            point.ref_time += ref_offset;
+            point.deg_time += deg_offset;
+        }
+        for point in &mut result.lag_curve {
+            point.ref_time += ref_offset;
+        }
+        for impairment in &mut result.impairments {
+            impairment.ref_start_time += ref_offset;
+            impairment.ref_end_time += ref_offset;
+            impairment.deg_start_time += deg_offset;
+            impairment.deg_end_time += deg_offset;
+        }
+        for point in &mut result.quality_trace {
+            point.ref_start_time += ref_offset;
+            point.ref_end_time += ref_offset;
+        }
+        Ok(result)
     }
 
     pub fn compute_results(
@@ -86,25 +458,169 @@ impl<const NUM_BANDS: usize> VisqolManager<NUM_BANDS> {
         ref_signal: &mut AudioSignal,
         deg_signal: &mut AudioSignal,
     ) -> Result<SimilarityResult, Box<dyn Error>> {
-        let (mut deg_signal, _) = alignment::globally_align(ref_signal, deg_signal)
-            .ok_or(VisqolError::FailedToAlignSignals)?;
+        self.compute_results_at(ref_signal, deg_signal, None)
+    }
+
+    /// Computes the results like `compute_results`, comparing `deg_signal` to the reference patches at `ref_patch_indices` if given.
+    fn compute_results_at(
+        &mut self,
+        ref_signal: &mut AudioSignal,
+        deg_signal: &mut AudioSignal,
+        ref_patch_indices: Option<&[usize]>,
+    ) -> Result<SimilarityResult, Box<dyn Error>> {
+        let mut result = self.compare_signals(ref_signal, deg_signal, ref_patch_indices)?;
+        let lag_curve = clock_drift::calculate_lag_curve(&result.patch_sims, result.global_delay);
+        let clock_drift_ppm = clock_drift::estimate_drift_ppm(&lag_curve);
+
+        if let Some(drift_ppm) = clock_drift_ppm.filter(|_| self.config.compensate_clock_drift) {
+            if clock_drift::is_compensable(&lag_curve, drift_ppm) {
+                log::info!("Compensating a clock drift of {drift_ppm} ppm.");
+                let mut compensated_deg_signal =
+                    clock_drift::compensate_drift(deg_signal, drift_ppm);
+                result = self.compare_signals(
+                    ref_signal,
+                    &mut compensated_deg_signal,
+                    ref_patch_indices,
+                )?;
+                Self::undo_drift_compensation(&mut result, drift_ppm);
+            } else {
+                log::warn!(
+                    "Not compensating a clock drift of {drift_ppm} ppm estimated from {} patches.",
+                    lag_curve.len()
+                );
+            }
+        }
+        result.clock_drift_ppm = clock_drift_ppm;
+        result.lag_curve = lag_curve;
+        // Measure the level of the degraded input, not of its aligned, warped or compensated copy.
+        result.deg_spl_db = audio_utils::calculate_sound_pressure_level(deg_signal);
+        Ok(result)
+    }
+
+    /// Moves the degraded times of `result`, which was computed on a signal compensated for `drift_ppm`, to the time of the original degraded signal.
+    fn undo_drift_compensation(result: &mut SimilarityResult, drift_ppm: f64) {
+        let global_delay = result.global_delay;
+        let to_drifted_time = |time| clock_drift::to_drifted_time(time, drift_ppm);
+        // Patch times do not include the global delay of the degraded signal.
+        for patch_sim in result
+            .patch_sims
+            .iter_mut()
+            .filter(|patch| patch.is_matched())
+        {
+            patch_sim.deg_patch_start_time =
+                to_drifted_time(patch_sim.deg_patch_start_time + global_delay) - global_delay;
+            patch_sim.deg_patch_end_time =
+                to_drifted_time(patch_sim.deg_patch_end_time + global_delay) - global_delay;
+        }
+        for point in result.warp_path.iter_mut().flatten() {
+            point.deg_time = to_drifted_time(point.deg_time);
+        }
+        for impairment in &mut result.impairments {
+            impairment.deg_start_time = to_drifted_time(impairment.deg_start_time);
+            impairment.deg_end_time = to_drifted_time(impairment.deg_end_time);
+        }
+    }
+
+    /// Aligns `deg_signal` to `ref_signal` and computes their similarity, comparing the reference patches at `ref_patch_indices` if given.
+    fn compare_signals(
+        &mut self,
+        ref_signal: &mut AudioSignal,
+        deg_signal: &mut AudioSignal,
+        ref_patch_indices: Option<&[usize]>,
+    ) -> Result<SimilarityResult, Box<dyn Error>> {
+        let (mut deg_signal, lag, alignment_confidence) = if self.config.disable_global_alignment {
+            (deg_signal.clone(), 0.0, None)
+        } else {
+            alignment::globally_align(ref_signal, deg_signal)
+                .ok_or(VisqolError::FailedToAlignSignals)?
+        };
+        let (mut warped_deg_signal, warp_path) = if self.config.enable_time_warping {
+            let (warped_deg_signal, warp_path) = time_warping::warp_to_reference(
+                ref_signal,
+                &deg_signal,
+                self.config.max_time_warp,
+            )?;
+            (Some(warped_deg_signal), Some(warp_path))
+        } else {
+            (None, None)
+        };
 
         let window = AnalysisWindow::new(
             ref_signal.sample_rate,
-            constants::OVERLAP,
-            constants::WINDOW_DURATION,
+            self.config.overlap,
+            self.config.window_duration,
         );
 
-        visqol::calculate_similarity(
+        let mut result = visqol::calculate_similarity(
             ref_signal,
-            &mut deg_signal,
-            &mut self.spectrogram_builder, // this does not need to be self
+            warped_deg_signal.as_mut().unwrap_or(&mut deg_signal),
+            self.spectrogram_builder.as_mut(),
             &window,
             self.patch_creator.as_mut(),
+            ref_patch_indices,
             &self.patch_selector,
             self.sim_to_quality_mapper.as_mut(),
-            self.search_window,
-        )
+            &self.config,
+        )?;
+        // The warped signal follows the timeline of the reference, locate the patches in the aligned signal instead.
+        if let Some(warp_path) = &warp_path {
+            for patch_sim in result
+                .patch_sims
+                .iter_mut()
+                .filter(|patch| patch.is_matched())
+            {
+                patch_sim.deg_patch_start_time =
+                    time_warping::to_degraded_time(warp_path, patch_sim.deg_patch_start_time);
+                patch_sim.deg_patch_end_time =
+                    time_warping::to_degraded_time(warp_path, patch_sim.deg_patch_end_time);
+            }
+        }
+        // The lag is the shift applied to the degraded signal, i.e. the negative delay.
+        result.global_delay = if lag == 0.0 { 0.0 } else { -lag };
+        result.alignment_confidence = alignment_confidence;
+        // Report the warp path in the time of the degraded signal before its global alignment.
+        result.warp_path = warp_path.map(|mut warp_path| {
+            for point in &mut warp_path {
+                point.deg_time += result.global_delay;
+            }
+            warp_path
+        });
+        result.impairments =
+            impairment_report::detect_impairments(&result.patch_sims, ref_signal, &deg_signal);
+        for impairment in &mut result.impairments {
+            impairment.deg_start_time += result.global_delay;
+            impairment.deg_end_time += result.global_delay;
+        }
+        Ok(result)
+    }
+
+    /// Converts a slice of floating point samples to `f64`.
+    fn to_double<T: Float>(samples: &[T]) -> Vec<f64> {
+        samples
+            .iter()
+            .map(|sample| sample.to_f64().expect("Failed to convert sample to f64!"))
+            .collect()
+    }
+
+    /// Returns the part of `signal` inside `range` and the start of the range in seconds.
+    fn cut(signal: AudioSignal, range: &TimeRange) -> Result<(AudioSignal, f64), VisqolError> {
+        if range.is_full() {
+            return Ok((signal, 0.0));
+        }
+        range.apply(&signal)
+    }
+
+    /// Returns `signal` resampled to the sample rate of the configured variant.
+    fn resample(&self, signal: AudioSignal) -> AudioSignal {
+        if signal.sample_rate == self.sample_rate {
+            return signal;
+        }
+        log::info!(
+            "Resampling signal from {} Hz to {} Hz.",
+            signal.sample_rate,
+            self.sample_rate
+        );
+        resampler::resample(&signal, self.sample_rate)
     }
 
     /// Performs sanity checks on the configuration to prevent incorrect use of the algorithm.
@@ -120,10 +636,662 @@ impl<const NUM_BANDS: usize> VisqolManager<NUM_BANDS> {
         }
 
         if (ref_signal.get_duration() - deg_signal.get_duration()).abs()
-            > constants::DURATION_MISMATCH_TOLERANCE
+            > DURATION_MISMATCH_TOLERANCE
         {
             log::warn!("Mismatch in duration between reference and degraded signal. Reference is {} seconds. Degraded is {} seconds.", ref_signal.get_duration(), deg_signal.get_duration());
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constants::{
+            DEFAULT_WINDOW_SIZE, MIN_CLOCK_DRIFT_LAG_POINTS, NARROWBAND_MAX_FREQ,
+            NARROWBAND_MIN_FREQ, NUM_BANDS_NARROWBAND, NUM_BANDS_SPEECH, NUM_BANDS_SUPER_WIDEBAND,
+            PATCH_SIZE_AUDIO,
+        },
+        impairment_report::ImpairmentKind,
+        mel_spectrogram_builder::MelSpectrogramBuilder,
+        patch_similarity_comparator::PatchSimilarityResult,
+        test_utility::{stretched_speech, wideband, wideband_manager, LinearMapper},
+        time_range::TimePosition,
+    };
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn signals_with_different_sample_rates_are_resampled() {
+        let mut visqol = wideband_manager();
+        let result_mixed = visqol
+            .run(
+                "test_data/clean_speech/reference_signal.wav",
+                "test_data/clean_speech/degraded_signal_16k.wav",
+            )
+            .unwrap();
+        let result_16k = visqol
+            .run(
+                "test_data/clean_speech/reference_signal_16k.wav",
+                "test_data/clean_speech/degraded_signal_16k.wav",
+            )
+            .unwrap();
+        assert_abs_diff_eq!(result_mixed.moslqo, result_16k.moslqo, epsilon = 0.01);
+    }
+
+    #[test]
+    fn interleaved_samples_score_like_files() {
+        let ref_path = "test_data/clean_speech/reference_signal_16k.wav";
+        let deg_path = "test_data/clean_speech/degraded_signal_16k.wav";
+        let mut visqol = wideband_manager();
+        let result_from_files = visqol.run(ref_path, deg_path).unwrap();
+
+        // Duplicate the mono signals into 2 identical channels.
+        let to_stereo = |file_path: &str| {
+            audio_utils::load_as_mono(file_path)
+                .unwrap()
+                .data_matrix
+                .iter()
+                .flat_map(|&sample| [sample as f32, sample as f32])
+                .collect::<Vec<f32>>()
+        };
+        let result_from_samples = visqol
+            .score_interleaved(&to_stereo(ref_path), &to_stereo(deg_path), 2, 16000)
+            .unwrap();
+        assert_abs_diff_eq!(
+            result_from_samples.moslqo,
+            result_from_files.moslqo,
+            epsilon = 0.0001
+        );
+    }
+
+    #[test]
+    fn channels_are_scored_separately() {
+        let mut visqol = wideband_manager();
+        let reference =
+            audio_utils::load_as_mono("test_data/clean_speech/reference_signal_16k.wav").unwrap();
+        let degraded =
+            audio_utils::load_as_mono("test_data/clean_speech/degraded_signal_16k.wav").unwrap();
+
+        // Only the first channel is degraded.
+        let result = visqol
+            .score_channels(
+                &[reference.clone(), reference.clone()],
+                &[degraded, reference],
+                &ChannelMode::PerChannel,
+            )
+            .unwrap();
+
+        assert_eq!(result.channels.len(), 2);
+        assert!(result.channels[0].result.moslqo < result.channels[1].result.moslqo);
+        assert_abs_diff_eq!(result.moslqo, result.channels[0].result.moslqo);
+        assert_abs_diff_eq!(
+            result.mean_moslqo,
+            (result.channels[0].result.moslqo + result.channels[1].result.moslqo) / 2.0
+        );
+    }
+
+    #[test]
+    fn time_ranges_are_cut_before_scoring() {
+        let mut visqol = wideband_manager();
+        let reference =
+            audio_utils::load_as_mono("test_data/clean_speech/reference_signal_16k.wav").unwrap();
+        let degraded =
+            audio_utils::load_as_mono("test_data/clean_speech/degraded_signal_16k.wav").unwrap();
+        let expected = visqol
+            .score_signals(reference.clone(), degraded.clone())
+            .unwrap();
+
+        // Prepend 1 second of a calibration tone to the reference and half a second to the degraded signal.
+        let prepend_tone = |signal: &AudioSignal, num_samples: usize| {
+            let mut samples = (0..num_samples)
+                .map(|i| (i as f64 * 0.2).sin() * 0.5)
+                .collect::<Vec<f64>>();
+            samples.extend(signal.data_matrix.iter());
+            AudioSignal::new(&samples, signal.sample_rate)
+        };
+        visqol.set_time_ranges(
+            TimeRange::new(Some(TimePosition::Seconds(1.0)), None),
+            TimeRange::new(Some(TimePosition::Samples(8000)), None),
+        );
+        let result = visqol
+            .score_signals(
+                prepend_tone(&reference, 16000),
+                prepend_tone(&degraded, 8000),
+            )
+            .unwrap();
+
+        assert_abs_diff_eq!(result.moslqo, expected.moslqo, epsilon = 0.0001);
+        for (patch, expected_patch) in result.patch_sims.iter().zip(&expected.patch_sims) {
+            assert_abs_diff_eq!(
+                patch.ref_patch_start_time,
+                expected_patch.ref_patch_start_time + 1.0,
+                epsilon = 0.0001
+            );
+            assert_abs_diff_eq!(
+                patch.deg_patch_end_time,
+                expected_patch.deg_patch_end_time + 0.5,
+                epsilon = 0.0001
+            );
+        }
+    }
+
+    #[cfg(feature = "lattice")]
+    #[test]
+    fn lattice_variant_scores_speech() {
+        let variant = Variant::WidebandLattice {
+            model_path: String::from("../model/model.onnx"),
+        };
+        let mut visqol = VisqolManager::new(variant, DEFAULT_WINDOW_SIZE).unwrap();
+        let result = visqol
+            .run(
+                "test_data/clean_speech/reference_signal_16k.wav",
+                "test_data/clean_speech/degraded_signal_16k.wav",
+            )
+            .unwrap();
+        assert_abs_diff_eq!(result.moslqo, 2.4545726776123047, epsilon = 0.0001);
+    }
+
+    #[cfg(feature = "lattice")]
+    #[test]
+    fn missing_lattice_model_is_rejected() {
+        let variant = || Variant::WidebandLattice {
+            model_path: String::from("/nonexistent.onnx"),
+        };
+        assert!(matches!(
+            VisqolManager::new(variant(), DEFAULT_WINDOW_SIZE),
+            Err(VisqolError::FailedToLoadModel { .. })
+        ));
+        assert!(VisqolManager::from_config(variant(), &VisqolConfig::default()).is_err());
+    }
+
+    #[cfg(feature = "lattice")]
+    #[test]
+    fn lattice_variant_requires_speech_bands() {
+        let variant = || Variant::WidebandLattice {
+            model_path: String::from("../model/model.onnx"),
+        };
+        let config = VisqolConfig::builder().num_bands(32).build().unwrap();
+        assert!(matches!(
+            VisqolManager::from_config(variant(), &config),
+            Err(VisqolError::InvalidConfig {
+                parameter: "num_bands",
+                ..
+            })
+        ));
+
+        let mut visqol = VisqolManager::new(variant(), DEFAULT_WINDOW_SIZE).unwrap();
+        assert!(visqol.set_frequency_bands(32, 100.0, 7000.0).is_err());
+        assert_eq!(visqol.config().num_bands, Some(NUM_BANDS_SPEECH));
+        visqol
+            .set_frequency_bands(NUM_BANDS_SPEECH, 100.0, 7000.0)
+            .unwrap();
+    }
+
+    #[test]
+    fn wideband_svr_mapping_uses_model() {
+        let variant = Variant::Wideband {
+            mapping: WidebandMapping::Svr {
+                model_path: String::from(
+                    "../model/tcdvoip_nu.568_c5.31474325639_g3.17773760038_model.txt",
+                ),
+            },
+        };
+        let mut visqol = VisqolManager::new(variant, DEFAULT_WINDOW_SIZE).unwrap();
+        let result = visqol
+            .run(
+                "test_data/clean_speech/reference_signal_16k.wav",
+                "test_data/clean_speech/degraded_signal_16k.wav",
+            )
+            .unwrap();
+        assert_abs_diff_eq!(result.moslqo, 2.005706310272217, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn custom_mapper_predicts_mos() {
+        let mut visqol =
+            VisqolManager::with_mapper(wideband(), DEFAULT_WINDOW_SIZE, Box::new(LinearMapper))
+                .unwrap();
+        let result = visqol
+            .run(
+                "test_data/clean_speech/reference_signal_16k.wav",
+                "test_data/clean_speech/degraded_signal_16k.wav",
+            )
+            .unwrap();
+
+        assert_abs_diff_eq!(result.moslqo, 1.0 + 4.0 * result.vnsim, epsilon = 1e-12);
+    }
+
+    /// Rates every pair of patches as identical.
+    struct IdenticalPatchesComparator;
+
+    impl PatchSimilarityComparator for IdenticalPatchesComparator {
+        fn measure_patch_similarity(
+            &self,
+            ref_patch: &mut ndarray::Array2<f64>,
+            _deg_patch: &mut ndarray::Array2<f64>,
+        ) -> PatchSimilarityResult {
+            let num_bands = ref_patch.nrows();
+            PatchSimilarityResult::new(
+                vec![1.0; num_bands],
+                vec![0.0; num_bands],
+                vec![1.0; num_bands],
+                1.0,
+            )
+        }
+    }
+
+    #[test]
+    fn custom_patch_similarity_comparator_is_used() {
+        let mut visqol = wideband_manager();
+        visqol.set_patch_similarity_comparator(Box::new(IdenticalPatchesComparator));
+        let result = visqol
+            .run(
+                "test_data/clean_speech/reference_signal_16k.wav",
+                "test_data/clean_speech/degraded_signal_16k.wav",
+            )
+            .unwrap();
+
+        assert_abs_diff_eq!(result.vnsim, 1.0);
+        assert!(result
+            .patch_sims
+            .iter()
+            .all(|patch_sim| patch_sim.similarity == 1.0));
+    }
+
+    #[test]
+    fn frequency_bands_are_configurable() {
+        let mut visqol = wideband_manager();
+        assert!(visqol.set_frequency_bands(32, 7000.0, 100.0).is_err());
+        visqol.set_frequency_bands(32, 100.0, 7000.0).unwrap();
+        let result = visqol
+            .run(
+                "test_data/clean_speech/reference_signal_16k.wav",
+                "test_data/clean_speech/degraded_signal_16k.wav",
+            )
+            .unwrap();
+
+        assert_eq!(result.fvnsim.len(), 32);
+        assert_abs_diff_eq!(result.center_freq_bands[0], 100.0, epsilon = 1e-6);
+        assert!(result.center_freq_bands[31] < 7000.0);
+    }
+
+    #[test]
+    fn default_config_is_conformant() {
+        let mut visqol = VisqolManager::from_config(wideband(), &VisqolConfig::default()).unwrap();
+        let result = visqol
+            .run(
+                "test_data/clean_speech/reference_signal_16k.wav",
+                "test_data/clean_speech/degraded_signal_16k.wav",
+            )
+            .unwrap();
+
+        assert_abs_diff_eq!(result.moslqo, 2.3529986568431522, epsilon = 1e-12);
+        assert_eq!(visqol.config().num_bands, Some(NUM_BANDS_SPEECH));
+        assert_eq!(visqol.config().search_window, DEFAULT_WINDOW_SIZE);
+    }
+
+    #[test]
+    fn invalid_config_is_rejected() {
+        let config = VisqolConfig {
+            window_duration: 0.0,
+            ..VisqolConfig::default()
+        };
+        assert!(VisqolManager::from_config(wideband(), &config).is_err());
+    }
+
+    #[test]
+    fn alignment_can_be_disabled() {
+        let config = VisqolConfig::builder()
+            .disable_global_alignment(true)
+            .disable_realignment(true)
+            .build()
+            .unwrap();
+        let mut visqol = VisqolManager::from_config(wideband(), &config).unwrap();
+        let reference = "test_data/clean_speech/reference_signal_16k.wav";
+        let result = visqol.run(reference, reference).unwrap();
+
+        assert_eq!(result.global_delay, 0.0);
+        assert!(result.alignment_confidence.is_none());
+        for patch_sim in &result.patch_sims {
+            assert_abs_diff_eq!(patch_sim.similarity, 1.0, epsilon = 1e-9);
+            assert_eq!(
+                patch_sim.ref_patch_start_time,
+                patch_sim.deg_patch_start_time
+            );
+        }
+    }
+
+    #[test]
+    fn global_delay_and_levels_are_reported() {
+        let mut visqol = wideband_manager();
+        let reference =
+            audio_utils::load_as_mono("test_data/clean_speech/reference_signal_16k.wav")
+                .unwrap()
+                .data_matrix
+                .to_vec();
+        // Delay the degraded signal by 100 ms and attenuate it by 6 dB.
+        let degraded = std::iter::repeat_n(0.0, 1600)
+            .chain(reference.iter().map(|sample| sample * 0.5))
+            .collect::<Vec<f64>>();
+
+        let result = visqol.score_samples(&reference, &degraded, 16000).unwrap();
+
+        assert_abs_diff_eq!(result.global_delay, 0.1, epsilon = 1e-3);
+        assert!(result.alignment_confidence.unwrap() > 0.9);
+        // The level of the degraded input includes the 100 ms of silence that global alignment removes.
+        let silence_db = 10.0 * (degraded.len() as f64 / reference.len() as f64).log10();
+        assert_abs_diff_eq!(
+            result.ref_spl_db - result.deg_spl_db,
+            6.02 + silence_db,
+            epsilon = 0.01
+        );
+    }
+
+    #[test]
+    fn time_warping_records_warp_path() {
+        let config = VisqolConfig::builder()
+            .enable_time_warping(true)
+            .build()
+            .unwrap();
+        let mut visqol = VisqolManager::from_config(wideband(), &config).unwrap();
+        let reference = "test_data/clean_speech/reference_signal_16k.wav";
+        let degraded = "test_data/clean_speech/degraded_signal_16k.wav";
+
+        let identical = visqol.run(reference, reference).unwrap();
+        let warp_path = identical.warp_path.unwrap();
+        assert!(!warp_path.is_empty());
+        for point in &warp_path {
+            assert_abs_diff_eq!(point.ref_time, point.deg_time, epsilon = 1e-9);
+        }
+        assert_abs_diff_eq!(identical.moslqo, 5.0, epsilon = 1e-3);
+
+        let result = visqol.run(reference, degraded).unwrap();
+        assert!(result.warp_path.is_some());
+        assert!(result.moslqo > 1.0 && result.moslqo < 5.0);
+    }
+
+    #[test]
+    fn warped_patches_are_located_in_the_degraded_signal() {
+        let config = VisqolConfig::builder()
+            .enable_time_warping(true)
+            .build()
+            .unwrap();
+        // The degraded signal is played 3% slower from its second second on.
+        let (reference, degraded) = stretched_speech();
+
+        let result = VisqolManager::from_config(wideband(), &config)
+            .unwrap()
+            .score_signals(reference, degraded)
+            .unwrap();
+
+        for patch_sim in &result.patch_sims {
+            let ref_time = patch_sim.ref_patch_end_time;
+            let expected = if ref_time < 1.0 {
+                ref_time
+            } else {
+                1.0 + (ref_time - 1.0) * 1.03
+            };
+            assert_abs_diff_eq!(
+                patch_sim.deg_patch_end_time + result.global_delay,
+                expected,
+                epsilon = 0.01
+            );
+        }
+    }
+
+    #[test]
+    fn impairments_are_located_in_the_unwarped_degraded_signal() {
+        let config = VisqolConfig::builder()
+            .enable_time_warping(true)
+            .build()
+            .unwrap();
+        let (reference, mut degraded) = stretched_speech();
+        // Zero 200 ms of active speech, 1.6 s into the stretched signal.
+        degraded
+            .data_matrix
+            .slice_mut(ndarray::s![25600..28800])
+            .fill(0.0);
+
+        let result = VisqolManager::from_config(wideband(), &config)
+            .unwrap()
+            .score_signals(reference, degraded)
+            .unwrap();
+        assert_eq!(result.impairments.len(), 1);
+        assert_eq!(result.impairments[0].kind, ImpairmentKind::Missing);
+        assert_abs_diff_eq!(result.impairments[0].deg_start_time, 1.6, epsilon = 0.005);
+        assert_abs_diff_eq!(result.impairments[0].deg_end_time, 1.8, epsilon = 0.005);
+    }
+
+    #[test]
+    fn clock_drift_is_estimated_and_compensated() {
+        let score = |degraded: &AudioSignal, patch_size: usize, compensate: bool| {
+            let config = VisqolConfig::builder()
+                .patch_size(patch_size)
+                .compensate_clock_drift(compensate)
+                .build()
+                .unwrap();
+            let reference =
+                audio_utils::load_as_mono("test_data/clean_speech/reference_signal_16k.wav")
+                    .unwrap();
+            VisqolManager::from_config(wideband(), &config)
+                .unwrap()
+                .score_signals(reference, degraded.clone())
+                .unwrap()
+        };
+        let reference =
+            audio_utils::load_as_mono("test_data/clean_speech/reference_signal_16k.wav").unwrap();
+        // Play the degraded signals 300 and 2000 ppm slower, so that they fall behind by 0.3 and 2 ms per second.
+        let slow = clock_drift::compensate_drift(&reference, 1e6 / 1.0003 - 1e6);
+        let too_slow = clock_drift::compensate_drift(&reference, 1e6 / 1.002 - 1e6);
+
+        // Short patches give enough matched patches to compensate the drift.
+        let drifted = score(&slow, 5, false);
+        let compensated = score(&slow, 5, true);
+        assert_eq!(drifted.lag_curve.len(), drifted.patch_sims.len());
+        assert!(drifted.lag_curve.len() >= MIN_CLOCK_DRIFT_LAG_POINTS);
+        assert_abs_diff_eq!(drifted.clock_drift_ppm.unwrap(), 300.0, epsilon = 100.0);
+        assert_eq!(compensated.clock_drift_ppm, drifted.clock_drift_ppm);
+        assert_ne!(compensated.moslqo, drifted.moslqo);
+
+        // The default patches are too few to compensate reliably.
+        let drifted = score(&slow, PATCH_SIZE_AUDIO, false);
+        let compensated = score(&slow, PATCH_SIZE_AUDIO, true);
+        assert!(drifted.lag_curve.len() < MIN_CLOCK_DRIFT_LAG_POINTS);
+        assert_eq!(compensated.moslqo, drifted.moslqo);
+
+        // A drift of 2000 ppm is reported, but not plausible enough to be compensated.
+        let drifted = score(&too_slow, 5, false);
+        let compensated = score(&too_slow, 5, true);
+        assert_abs_diff_eq!(drifted.clock_drift_ppm.unwrap(), 2000.0, epsilon = 300.0);
+        assert_eq!(compensated.moslqo, drifted.moslqo);
+    }
+
+    #[test]
+    fn dropouts_are_reported_as_impairments() {
+        let reference =
+            audio_utils::load_as_mono("test_data/clean_speech/reference_signal_16k.wav").unwrap();
+        let mut degraded = reference.clone();
+        // Zero 200 ms of active speech.
+        let dropout = 16000..19200;
+        degraded
+            .data_matrix
+            .slice_mut(ndarray::s![dropout.clone()])
+            .fill(0.0);
+
+        let mut visqol = wideband_manager();
+        let clean = visqol
+            .score_signals(reference.clone(), reference.clone())
+            .unwrap();
+        let result = visqol.score_signals(reference, degraded).unwrap();
+
+        assert!(clean.impairments.is_empty());
+        assert!(result.impairments.iter().any(|impairment| {
+            impairment.kind == ImpairmentKind::Missing
+                && impairment.deg_start_time < 1.2
+                && impairment.deg_end_time > 1.0
+        }));
+    }
+
+    #[test]
+    fn quality_trace_drops_at_the_impairment() {
+        let reference =
+            audio_utils::load_as_mono("test_data/clean_speech/reference_signal_16k.wav").unwrap();
+        let mut degraded = reference.clone();
+        // Zero 500 ms of active speech.
+        degraded
+            .data_matrix
+            .slice_mut(ndarray::s![16000..24000])
+            .fill(0.0);
+
+        let mut visqol = wideband_manager();
+        let result = visqol
+            .score_signals(reference.clone(), degraded.clone())
+            .unwrap();
+        let config = VisqolConfig::builder()
+            .quality_trace_patches(3)
+            .build()
+            .unwrap();
+        let mut visqol = VisqolManager::from_config(wideband(), &config).unwrap();
+        let grouped = visqol.score_signals(reference, degraded).unwrap();
+
+        assert_eq!(result.quality_trace.len(), result.patch_sims.len());
+        assert_eq!(grouped.quality_trace.len(), grouped.patch_sims.len() - 2);
+        let worst = result
+            .quality_trace
+            .iter()
+            .min_by(|a, b| a.moslqo.total_cmp(&b.moslqo))
+            .unwrap();
+        assert!(worst.ref_start_time < 1.5 && worst.ref_end_time > 1.0);
+        assert!(worst.moslqo < result.moslqo);
+    }
+
+    #[test]
+    fn bootstrap_interval_contains_the_mos() {
+        let config = VisqolConfig::builder()
+            .bootstrap_iterations(200)
+            .build()
+            .unwrap();
+        let mut visqol = VisqolManager::from_config(wideband(), &config).unwrap();
+        let result = visqol
+            .run(
+                "test_data/clean_speech/reference_signal_16k.wav",
+                "test_data/clean_speech/degraded_signal_16k.wav",
+            )
+            .unwrap();
+
+        let interval = result.moslqo_confidence.unwrap();
+        assert_eq!(interval.iterations, 200);
+        assert!(interval.lower <= result.moslqo && result.moslqo <= interval.upper);
+        assert!(interval.standard_error > 0.0);
+    }
+
+    #[test]
+    fn ab_comparison_prefers_the_better_signal() {
+        let config = VisqolConfig::builder()
+            .bootstrap_iterations(100)
+            .build()
+            .unwrap();
+        let mut visqol = VisqolManager::from_config(wideband(), &config).unwrap();
+        let comparison = visqol
+            .run_ab(
+                "test_data/clean_speech/reference_signal_16k.wav",
+                "test_data/clean_speech/degraded_signal_16k.wav",
+                "test_data/clean_speech/reference_signal_16k.wav",
+            )
+            .unwrap();
+
+        assert_eq!(
+            comparison.result_a.patch_sims.len(),
+            comparison.result_b.patch_sims.len()
+        );
+        assert_abs_diff_eq!(
+            comparison.moslqo_difference,
+            comparison.result_a.moslqo - comparison.result_b.moslqo
+        );
+        assert!(comparison.moslqo_difference < 0.0);
+        assert!(comparison.upper < 0.0);
+        assert!(comparison.p_value < 0.05);
+        assert_eq!(comparison.iterations, 100);
+        assert!(comparison.result_a.moslqo_confidence.is_none());
+        assert!(comparison.result_b.moslqo_confidence.is_none());
+        assert_eq!(visqol.config().bootstrap_iterations, 100);
+    }
+
+    #[test]
+    fn narrowband_signals_are_scored_at_8_khz() {
+        let variant = || Variant::Narrowband {
+            use_unscaled_mos_mapping: false,
+        };
+        let mut visqol = VisqolManager::new(variant(), DEFAULT_WINDOW_SIZE).unwrap();
+        let reference =
+            audio_utils::load_as_mono("test_data/clean_speech/reference_signal_16k.wav").unwrap();
+        let degraded =
+            audio_utils::load_as_mono("test_data/clean_speech/degraded_signal_16k.wav").unwrap();
+        let reference = resampler::resample(&reference, 8000);
+        let degraded = resampler::resample(&degraded, 8000);
+
+        let clean = visqol
+            .score_signals(reference.clone(), reference.clone())
+            .unwrap();
+        let result = visqol.score_signals(reference, degraded).unwrap();
+
+        assert_eq!(visqol.sample_rate, 8000);
+        assert_eq!(result.center_freq_bands.len(), NUM_BANDS_NARROWBAND);
+        assert!(result
+            .center_freq_bands
+            .iter()
+            .all(|&freq| (NARROWBAND_MIN_FREQ..=NARROWBAND_MAX_FREQ).contains(&freq)));
+        assert_abs_diff_eq!(clean.moslqo, 5.0, epsilon = 0.01);
+        assert!(result.moslqo > 1.0 && result.moslqo < clean.moslqo);
+    }
+
+    #[test]
+    fn super_wideband_signals_are_scored_up_to_16_khz() {
+        let variant = Variant::SuperWideband {
+            use_unscaled_mos_mapping: false,
+        };
+        let mut visqol = VisqolManager::new(variant, DEFAULT_WINDOW_SIZE).unwrap();
+        let reference =
+            audio_utils::load_as_mono("test_data/clean_speech/reference_signal.wav").unwrap();
+        // Limit the bandwidth to 8 kHz, like a wideband codec.
+        let wideband = resampler::resample(&resampler::resample(&reference, 16000), 48000);
+
+        let clean = visqol
+            .score_signals(reference.clone(), reference.clone())
+            .unwrap();
+        let result = visqol.score_signals(reference, wideband).unwrap();
+
+        assert_eq!(visqol.sample_rate, 32000);
+        assert_eq!(result.center_freq_bands.len(), NUM_BANDS_SUPER_WIDEBAND);
+        assert!(result.center_freq_bands.iter().any(|&freq| freq > 8000.0));
+        assert_abs_diff_eq!(clean.moslqo, 5.0, epsilon = 0.01);
+        assert!(result.moslqo < clean.moslqo);
+    }
+
+    #[test]
+    fn mel_spectrogram_builder_is_used() {
+        let mut visqol = wideband_manager();
+        visqol.set_spectrogram_builder(Box::new(MelSpectrogramBuilder::new(40, 50.0, 8000.0)));
+        let reference = "test_data/clean_speech/reference_signal_16k.wav";
+
+        let identical = visqol.run(reference, reference).unwrap();
+        let degraded = visqol
+            .run(reference, "test_data/clean_speech/degraded_signal_16k.wav")
+            .unwrap();
+
+        assert_eq!(degraded.fvnsim.len(), 40);
+        assert_eq!(degraded.center_freq_bands.len(), 40);
+        assert!(identical.vnsim > 0.99);
+        assert!(degraded.moslqo < identical.moslqo);
+    }
+
+    #[test]
+    fn interleaved_samples_must_fill_all_channels() {
+        let mut visqol = wideband_manager();
+        let samples = vec![0.0f64; 16001];
+        assert!(visqol
+            .score_interleaved(&samples, &samples, 2, 16000)
+            .is_err());
+    }
+}
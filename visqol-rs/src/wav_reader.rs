@@ -1,5 +1,5 @@
-use crate::visqol_error;
-use hound::WavReader;
+use crate::{math_utils, visqol_error};
+use hound::{SampleFormat, WavReader};
 use std::error::Error;
 /// Represents the metadata and contents of a wav file.
 /// Simple wrapper around the `hound` library.
@@ -8,33 +8,38 @@ pub struct WavFile {
     pub num_channels: u16,
     /// The sample rate of the wav file
     pub sample_rate: u32,
-    /// The samples in the wav file. Note that these are not scaled from -1.0 to 1.0 but its integer values.
-    pub samples: Vec<i16>,
+    /// The interleaved samples in the wav file, scaled from -1.0 to 1.0 regardless of the stored bit depth.
+    pub samples: Vec<f64>,
 }
 
 impl WavFile {
     /// given a `file_path` to the desired wav file, the contents of the wav file are returned.
+    /// 8, 16, 24 and 32 bit integer as well as 32 bit float files are supported.
     /// Any possible errors are reported by `hound`.
     pub fn open(file_path: &str) -> Result<Self, Box<dyn Error>> {
         let mut reader = WavReader::open(file_path)?;
         let spec = reader.spec();
 
-        if spec.bits_per_sample != 16 {
-            return Err(Box::new(visqol_error::VisqolError::InvalidBitsPerSample {
-                bits_per_sample: spec.bits_per_sample,
-            }));
-        }
-
-        let samples: Vec<i32> = reader
-            .samples::<i32>()
-            .map(|x| x.expect("Failed to read samples"))
-            .collect();
-        let samples_quantized = samples.iter().map(|&e| e as i16).collect();
+        let samples = match (spec.sample_format, spec.bits_per_sample) {
+            (SampleFormat::Int, 8 | 16 | 24 | 32) => {
+                let samples = reader.samples::<i32>().collect::<Result<Vec<_>, _>>()?;
+                math_utils::normalize_int_to_double(&samples, spec.bits_per_sample)
+            }
+            (SampleFormat::Float, 32) => reader
+                .samples::<f32>()
+                .map(|sample| sample.map(f64::from))
+                .collect::<Result<Vec<_>, _>>()?,
+            _ => {
+                return Err(Box::new(visqol_error::VisqolError::InvalidBitsPerSample {
+                    bits_per_sample: spec.bits_per_sample,
+                }))
+            }
+        };
 
         Ok(Self {
             num_channels: spec.channels,
             sample_rate: spec.sample_rate,
-            samples: samples_quantized,
+            samples,
         })
     }
 }
@@ -0,0 +1,118 @@
+use crate::similarity_result::SimilarityResult;
+use serde::Serialize;
+
+/// Number of frequency bands reported per segment
+const NUM_WORST_BANDS: usize = 3;
+
+/// How much less similar a frequency band of a segment is than the same band of the whole signal
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct BandDrop {
+    /// Center frequency of the band in Hz
+    pub center_freq: f64,
+    /// Similarity of the band over the whole signal minus its similarity in the segment
+    pub similarity_drop: f64,
+}
+
+/// A patch of the reference and its matched degraded patch, located in both signals
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WorstSegment {
+    /// Reference start of the segment in seconds
+    pub ref_start_time: f64,
+    /// Reference end of the segment in seconds
+    pub ref_end_time: f64,
+    /// Degraded start of the segment in seconds, before the global alignment of the degraded signal.
+    /// Equal to `deg_end_time` if no degraded patch was matched.
+    pub deg_start_time: f64,
+    /// Degraded end of the segment in seconds
+    pub deg_end_time: f64,
+    /// Similarity of the segment
+    pub similarity: f64,
+    /// Bands whose similarity dropped the most in this segment, ordered from the largest drop
+    pub worst_bands: Vec<BandDrop>,
+}
+
+/// Returns the `count` patches of `result` with the lowest similarity, ordered from the least similar.
+/// Each segment lists up to 3 bands that are less similar than in the whole signal, ordered by how much their similarity dropped.
+/// Degraded times include the global delay, so that they point into the degraded signal like those of the impairments.
+pub fn find_worst_segments(result: &SimilarityResult, count: usize) -> Vec<WorstSegment> {
+    let mut patches = result.patch_sims.iter().collect::<Vec<_>>();
+    patches.sort_by(|a, b| a.similarity.total_cmp(&b.similarity));
+
+    patches
+        .into_iter()
+        .take(count)
+        .map(|patch| {
+            let mut worst_bands = patch
+                .freq_band_means
+                .iter()
+                .zip(&result.fvnsim)
+                .zip(&result.center_freq_bands)
+                .map(|((&band_mean, &signal_mean), &center_freq)| BandDrop {
+                    center_freq,
+                    similarity_drop: signal_mean - band_mean,
+                })
+                .filter(|band| band.similarity_drop > 0.0)
+                .collect::<Vec<_>>();
+            worst_bands.sort_by(|a, b| b.similarity_drop.total_cmp(&a.similarity_drop));
+            worst_bands.truncate(NUM_WORST_BANDS);
+
+            let global_delay = if patch.is_matched() {
+                result.global_delay
+            } else {
+                0.0
+            };
+            WorstSegment {
+                ref_start_time: patch.ref_patch_start_time,
+                ref_end_time: patch.ref_patch_end_time,
+                deg_start_time: patch.deg_patch_start_time + global_delay,
+                deg_end_time: patch.deg_patch_end_time + global_delay,
+                similarity: patch.similarity,
+                worst_bands,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utility::patch;
+
+    #[test]
+    fn least_similar_patches_are_ranked_with_their_worst_bands() {
+        let patch_sims = vec![
+            patch(0.0, 0.0 + 0.1, vec![0.9, 0.9, 0.9, 0.9, 0.9]),
+            patch(0.5, 0.5 + 0.1, vec![0.9, 0.3, 0.8, 0.5, 0.95]),
+            patch(1.0, 1.0 + 0.1, vec![0.9, 0.9, 0.6, 0.9, 0.9]),
+            patch(1.5, 1.5 + 0.1, vec![1.0; 5]),
+        ];
+        let mut result = SimilarityResult::new(
+            4.0,
+            0.8,
+            vec![0.9, 0.7, 0.77, 0.77, 0.9],
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+            vec![100.0, 200.0, 400.0, 800.0, 1600.0],
+            patch_sims,
+        );
+
+        result.global_delay = 0.25;
+        let worst = find_worst_segments(&result, 2);
+
+        assert_eq!(worst.len(), 2);
+        assert_eq!(worst[0].ref_start_time, 0.5);
+        assert_eq!(worst[0].deg_start_time, 0.85);
+        assert_eq!(
+            worst[0]
+                .worst_bands
+                .iter()
+                .map(|band| band.center_freq)
+                .collect::<Vec<_>>(),
+            vec![200.0, 800.0]
+        );
+        assert_eq!(worst[1].ref_start_time, 1.0);
+        assert_eq!(worst[1].worst_bands.len(), 1);
+        assert_eq!(find_worst_segments(&result, 10).len(), 4);
+    }
+}
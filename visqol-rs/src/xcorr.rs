@@ -1,6 +1,7 @@
 use crate::fast_fourier_transform;
 use crate::fft_manager::FftManager;
 use num::complex::Complex64;
+use std::convert::TryFrom;
 
 /// Calculate the maximum delay between to signals.
 pub fn calculate_best_lag(signal_1: &[f64], signal_2: &[f64]) -> Option<i64> {
@@ -27,6 +28,34 @@ pub fn calculate_best_lag(signal_1: &[f64], signal_2: &[f64]) -> Option<i64> {
     Some(best_corr_idx as i64 - max_lag)
 }
 
+/// Calculates the Pearson correlation of 2 signals after shifting `signal_2` by `lag` samples, as returned by `calculate_best_lag`.
+/// Only the overlapping samples are compared. Returns `None` if they overlap by less than 2 samples or either overlap is constant.
+pub fn calculate_correlation_at_lag(signal_1: &[f64], signal_2: &[f64], lag: i64) -> Option<f64> {
+    let pairs = (0..signal_1.len())
+        .filter_map(|index| {
+            let shifted = usize::try_from(index as i64 - lag).ok()?;
+            Some((signal_1[index], *signal_2.get(shifted)?))
+        })
+        .collect::<Vec<(f64, f64)>>();
+    if pairs.len() < 2 {
+        return None;
+    }
+
+    let count = pairs.len() as f64;
+    let mean_1 = pairs.iter().map(|(a, _)| a).sum::<f64>() / count;
+    let mean_2 = pairs.iter().map(|(_, b)| b).sum::<f64>() / count;
+    let (mut covariance, mut variance_1, mut variance_2) = (0.0, 0.0, 0.0);
+    for (a, b) in &pairs {
+        covariance += (a - mean_1) * (b - mean_2);
+        variance_1 += (a - mean_1).powi(2);
+        variance_2 += (b - mean_2).powi(2);
+    }
+    if variance_1 == 0.0 || variance_2 == 0.0 {
+        return None;
+    }
+    Some(covariance / (variance_1 * variance_2).sqrt())
+}
+
 /// Calculates the pointwise inverse fft product of 2 signals
 pub fn calculate_inverse_fft_pointwise_product(
     signal_1: &mut Vec<f64>,
@@ -179,6 +208,23 @@ mod tests {
         assert_eq!(best_lag, expected_result);
     }
 
+    #[test]
+    fn correlation_at_best_lag_is_high() {
+        let ref_signal = vec![
+            2.0, 2.0, 1.0, 0.1, -3.0, 0.1, 1.0, 2.0, 2.0, 6.0, 8.0, 6.0, 2.0, 2.0,
+        ];
+        let deg_signal_lag2 = vec![
+            2.0, 2.0, 2.0, 2.0, 1.0, 0.1, -3.0, 0.1, 1.0, 2.0, 2.0, 6.0, 8.0, 6.0,
+        ];
+
+        let aligned = calculate_correlation_at_lag(&ref_signal, &deg_signal_lag2, -2).unwrap();
+        let misaligned = calculate_correlation_at_lag(&ref_signal, &deg_signal_lag2, 0).unwrap();
+
+        assert!((aligned - 1.0).abs() < 1e-12);
+        assert!(misaligned < aligned);
+        assert!(calculate_correlation_at_lag(&ref_signal, &[1.0; 14], 0).is_none());
+    }
+
     #[test]
     fn test_frexp() {
         let (_, result) = frexp(27.0f64);
@@ -1,8 +1,20 @@
-use std::{error::Error, path::PathBuf};
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+};
 
 use crate::path_pair::PathPair;
 use clap::{Parser, Subcommand};
 use csv::{ReaderBuilder, StringRecord};
+use visqol_rs::{
+    audio_source::RawFormat,
+    channel_mode::ChannelMode,
+    time_range::{TimePosition, TimeRange},
+    visqol_config::VisqolConfig,
+};
+
+/// Search window radius of the command line tool if neither `--search_window_radius` nor the config file set it.
+const DEFAULT_SEARCH_WINDOW_RADIUS: usize = 60;
 
 #[derive(Subcommand, Clone, Debug)]
 pub enum Subcommands {
@@ -15,6 +27,29 @@ pub enum Subcommands {
         /// Perfect NSIM scores will instead result in MOS scores of ~4.x. [default: false]
         #[clap(long = "use_unscaled_speech_mos_mapping")]
         use_unscaled_speech_mos_mapping: bool,
+        /// Map similarity to MOS with the ONNX lattice model of ViSQOL v3.3
+        /// instead of the polynomial mapping. Optionally takes the path of
+        /// the model file. [default: ./model/model.onnx]
+        #[clap(
+            long = "use_lattice_model",
+            num_args = 0..=1,
+            default_missing_value = "./model/model.onnx",
+            conflicts_with_all = ["use_unscaled_speech_mos_mapping", "use_svr_model"]
+        )]
+        use_lattice_model: Option<String>,
+        /// Map similarity to MOS with support vector regression instead of
+        /// the polynomial mapping. Optionally takes the path of the libsvm
+        /// model file. [default: the experimental TCD-VoIP speech model,
+        /// ./model/tcdvoip_nu.568_c5.31474325639_g3.17773760038_model.txt]
+        #[clap(
+            long = "use_svr_model",
+            num_args = 0..=1,
+            default_missing_value = "./model/tcdvoip_nu.568_c5.31474325639_g3.17773760038_model.txt",
+            conflicts_with = "use_unscaled_speech_mos_mapping"
+        )]
+        use_svr_model: Option<String>,
+        #[command(subcommand)]
+        comparison: Option<Comparison>,
     },
     /// Evaluate fullband signals at 48 kHz sample rate.
     /// Predictions are made using a support vector machine.
@@ -27,6 +62,43 @@ pub enum Subcommands {
             default_value = "./model/libsvm_nu_svr_model.txt"
         )]
         similarity_to_quality_model: String,
+        #[command(subcommand)]
+        comparison: Option<Comparison>,
+    },
+    /// Evaluate narrowband telephony speech at 8 kHz sample rate, e.g.
+    /// G.711 or AMR-NB, with voice activity detection. Only the telephone
+    /// band from 300 Hz to 3.4 kHz is compared.
+    Narrowband {
+        /// Perfect NSIM scores will instead result in MOS scores of ~4.x. [default: false]
+        #[clap(long = "use_unscaled_speech_mos_mapping")]
+        use_unscaled_speech_mos_mapping: bool,
+        #[command(subcommand)]
+        comparison: Option<Comparison>,
+    },
+    /// Evaluate super-wideband speech at 32 kHz sample rate, e.g. Opus or
+    /// EVS in super-wideband mode, with voice activity detection. Frequency
+    /// bands up to 16 kHz are compared.
+    #[command(name = "super_wideband")]
+    SuperWideband {
+        /// Perfect NSIM scores will instead result in MOS scores of ~4.x. [default: false]
+        #[clap(long = "use_unscaled_speech_mos_mapping")]
+        use_unscaled_speech_mos_mapping: bool,
+        #[command(subcommand)]
+        comparison: Option<Comparison>,
+    },
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum Comparison {
+    /// Scores the degraded file (A) and a second degraded file (B) against
+    /// the same patches of the reference file and tests whether their MOS
+    /// differ with a paired bootstrap over the patches. Reports the MOS
+    /// difference A - B, its confidence interval and a p-value.
+    #[command(name = "compare_ab")]
+    CompareAb {
+        /// The second degraded audio file (B).
+        #[clap(long = "degraded_file_b")]
+        degraded_file_b: String,
     },
 }
 
@@ -54,7 +126,7 @@ pub struct CommandLineArgs {
     )]
     pub batch_input_csv: Option<String>,
 
-    /// The wav file path used as the reference audio.
+    /// The audio file path used as the reference audio. Supports wav, flac, ogg (vorbis and opus), mp3 and aiff files.
     #[clap(
         long = "reference_file",
         requires = "degraded-file",
@@ -62,7 +134,7 @@ pub struct CommandLineArgs {
     )]
     pub reference_file: Option<String>,
 
-    /// The wav file path used as the degraded audio.
+    /// The audio file path used as the degraded audio. Supports wav, flac, ogg (vorbis and opus), mp3 and aiff files.
     #[clap(
         long = "degraded_file",
         requires = "reference-file",
@@ -70,12 +142,45 @@ pub struct CommandLineArgs {
     )]
     pub degraded_file: Option<String>,
 
+    /// Reads the reference file(s) as headerless raw PCM with the given
+    /// format instead of detecting the format from the file header.{n}
+    /// Format: <s16le|f32le>:<sample rate>[:<channels>], e.g. s16le:48000:2
+    #[clap(long = "reference_format")]
+    pub reference_format: Option<RawFormat>,
+
+    /// Reads the degraded file(s) as headerless raw PCM with the given
+    /// format instead of detecting the format from the file header.{n}
+    /// Format: <s16le|f32le>:<sample rate>[:<channels>], e.g. s16le:48000:2
+    #[clap(long = "degraded_format")]
+    pub degraded_format: Option<RawFormat>,
+
+    /// Start of the part of the reference file(s) that is scored. Given in
+    /// seconds (e.g. 1.5 or 1.5s) or in samples (e.g. 24000samples).
+    /// Patch times in the output stay relative to the start of the file.
+    #[clap(long = "reference_start")]
+    pub reference_start: Option<TimePosition>,
+
+    /// End of the part of the reference file(s) that is scored, in seconds
+    /// or samples.
+    #[clap(long = "reference_end")]
+    pub reference_end: Option<TimePosition>,
+
+    /// Start of the part of the degraded file(s) that is scored, in seconds
+    /// or samples.
+    #[clap(long = "degraded_start")]
+    pub degraded_start: Option<TimePosition>,
+
+    /// End of the part of the degraded file(s) that is scored, in seconds
+    /// or samples.
+    #[clap(long = "degraded_end")]
+    pub degraded_end: Option<TimePosition>,
+
     /// Used to specify a path that the similarity score results will be
-    /// stored in. This will be a CSV file with the format:{n}
+    /// stored in. This will be a CSV file with one row per compared channel,
+    /// starting with the channel label and the MOS:{n}
     /// ------------------{n}
-    /// reference,degraded,moslqo{n}
-    /// ref1.wav,deg1.wav,3.4{n}
-    /// ref2.wav,deg2.wav,4.1{n}
+    /// mid,3.4,...{n}
+    /// side,4.1,...{n}
     #[clap(long = "results_csv")]
     pub results_csv: Option<String>,
 
@@ -94,12 +199,165 @@ pub struct CommandLineArgs {
     #[clap(long = "output_debug")]
     pub output_debug: Option<String>,
 
+    /// Scores each channel of the reference file against the same channel
+    /// of the degraded file instead of scoring the mono downmix. The
+    /// lowest and the mean MOS of all channels are reported as well.
+    #[clap(long = "per_channel", conflicts_with_all = ["mid_side", "channel"])]
+    pub per_channel: bool,
+
+    /// Scores the mid (L+R) and side (L-R) signals of stereo files instead
+    /// of the mono downmix.
+    #[clap(long = "mid_side", conflicts_with = "channel")]
+    pub mid_side: bool,
+
+    /// Scores only the given channel, counting from 0. Can be specified
+    /// multiple times to score several channels separately.
+    #[clap(long = "channel")]
+    pub channel: Vec<usize>,
+
     /// The search_window parameter determines how far the algorithm will
     /// search to discover patch matches. For a given reference frame, it
     /// will look at 2*search_window_radius + 1 patches to find the most
-    /// optimal match.
-    #[clap(long = "search_window_radius", default_value_t = 60)]
-    pub search_window_radius: usize,
+    /// optimal match. Overrides the search_window of the config file.
+    /// [default: 60]
+    #[clap(long = "search_window_radius")]
+    pub search_window_radius: Option<usize>,
+
+    /// Path of a TOML or JSON file that overrides parameters of the
+    /// ViSQOL stages, e.g.{n}
+    /// ------------------{n}
+    /// window_duration = 0.08{n}
+    /// overlap = 0.25{n}
+    /// patch_size = 20{n}
+    /// num_bands = 32{n}
+    /// min_freq = 50.0{n}
+    /// max_freq = 8000.0{n}
+    /// [noise_floor]{n}
+    /// absolute_db = -45.0{n}
+    /// relative_to_peak_db = 45.0{n}
+    /// ------------------{n}
+    /// Parameters that are not given keep their conformant defaults. Files
+    /// ending in .toml are read as TOML, all other files as JSON.
+    #[clap(long = "config")]
+    pub config: Option<String>,
+
+    /// Disables the global alignment of the degraded file to the reference
+    /// file. Useful if the files are known to be sample-aligned.
+    #[clap(long = "disable_global_alignment")]
+    pub disable_global_alignment: bool,
+
+    /// Disables the fine realignment of each pair of matched patches in
+    /// the time domain.
+    #[clap(long = "disable_realignment")]
+    pub disable_realignment: bool,
+
+    /// Warps the timeline of the degraded file to follow the reference
+    /// file, e.g. to compensate jitter buffers or time stretching.
+    #[clap(long = "enable_time_warping")]
+    pub enable_time_warping: bool,
+
+    /// Largest drift in seconds between the files that time warping
+    /// follows. Overrides the max_time_warp of the config file.
+    #[clap(long = "max_time_warp")]
+    pub max_time_warp: Option<f64>,
+
+    /// Resamples the degraded file to remove the clock drift between the
+    /// recording devices, then scores it again.
+    #[clap(long = "compensate_clock_drift")]
+    pub compensate_clock_drift: bool,
+
+    /// Lists the N least similar patches with their time ranges in both
+    /// files and the frequency bands whose similarity dropped the most.
+    /// They are also written to `worst_segments` in the debug JSON.
+    #[clap(long = "report_worst")]
+    pub report_worst: Option<usize>,
+
+    /// Estimates a confidence interval of the MOS from N resampled sets of
+    /// the compared patches. Overrides the bootstrap_iterations of the
+    /// config file.
+    #[clap(long = "bootstrap_iterations")]
+    pub bootstrap_iterations: Option<usize>,
+}
+
+impl CommandLineArgs {
+    /// Returns the comparison selected after the mode, if any.
+    pub fn comparison(&self) -> Option<&Comparison> {
+        match &self.subcommand {
+            Subcommands::Wideband { comparison, .. }
+            | Subcommands::Fullband { comparison, .. }
+            | Subcommands::Narrowband { comparison, .. }
+            | Subcommands::SuperWideband { comparison, .. } => comparison.as_ref(),
+        }
+    }
+
+    /// Returns the channel mode selected by the `per_channel`, `mid_side` and `channel` flags.
+    pub fn channel_mode(&self) -> ChannelMode {
+        if self.per_channel {
+            ChannelMode::PerChannel
+        } else if self.mid_side {
+            ChannelMode::MidSide
+        } else if !self.channel.is_empty() {
+            ChannelMode::Select(self.channel.clone())
+        } else {
+            ChannelMode::Downmix
+        }
+    }
+
+    /// Returns the scored time range of the reference file(s).
+    pub fn reference_range(&self) -> TimeRange {
+        TimeRange::new(self.reference_start, self.reference_end)
+    }
+
+    /// Returns the scored time range of the degraded file(s).
+    pub fn degraded_range(&self) -> TimeRange {
+        TimeRange::new(self.degraded_start, self.degraded_end)
+    }
+
+    /// Returns the configuration of the `config` file, or the default configuration if no file is given.
+    /// The search window is set by `search_window_radius` if given, alignment steps are disabled by the `disable_*` flags.
+    pub fn visqol_config(&self) -> Result<VisqolConfig, Box<dyn Error>> {
+        let mut config = match &self.config {
+            Some(config_path) => load_config(config_path)?,
+            None => VisqolConfig {
+                search_window: DEFAULT_SEARCH_WINDOW_RADIUS,
+                ..VisqolConfig::default()
+            },
+        };
+        if let Some(search_window_radius) = self.search_window_radius {
+            config.search_window = search_window_radius;
+        }
+        config.disable_global_alignment |= self.disable_global_alignment;
+        config.disable_realignment |= self.disable_realignment;
+        config.enable_time_warping |= self.enable_time_warping;
+        if let Some(max_time_warp) = self.max_time_warp {
+            config.max_time_warp = max_time_warp;
+        }
+        config.compensate_clock_drift |= self.compensate_clock_drift;
+        if let Some(bootstrap_iterations) = self.bootstrap_iterations {
+            config.bootstrap_iterations = bootstrap_iterations;
+        }
+        Ok(config)
+    }
+}
+
+/// Reads a `VisqolConfig` from the TOML or JSON file in `config_path`.
+/// The search window defaults to the one of the command line tool.
+pub fn load_config(config_path: &str) -> Result<VisqolConfig, Box<dyn Error>> {
+    let contents = std::fs::read_to_string(config_path)?;
+    let is_toml = Path::new(config_path)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("toml"));
+    let mut config: serde_json::Value = if is_toml {
+        toml::from_str(&contents)?
+    } else {
+        serde_json::from_str(&contents)?
+    };
+    if let Some(parameters) = config.as_object_mut() {
+        parameters
+            .entry("search_window")
+            .or_insert(DEFAULT_SEARCH_WINDOW_RADIUS.into());
+    }
+    Ok(serde_json::from_value(config)?)
 }
 
 pub fn build_file_pair_paths(args: &CommandLineArgs) -> Result<Vec<PathPair>, Box<dyn Error>> {
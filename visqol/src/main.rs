@@ -1,37 +1,110 @@
 use clap::Parser;
 use log::LevelFilter;
-use simplelog::{ColorChoice, Config, TermLogger, TerminalMode};
+use simplelog::{ColorChoice, ConfigBuilder, TermLogger, TerminalMode};
 use std::error::Error;
 
 use visqol_rs::{
-    constants::{NUM_BANDS_AUDIO, NUM_BANDS_SPEECH},
-    similarity_result::SimilarityResult,
-    variant::Variant,
+    ab_comparison::AbComparisonResult,
+    audio_signal::AudioSignal,
+    audio_source::RawFormat,
+    audio_utils,
+    channel_mode::ChannelMode,
+    similarity_result::MultiChannelSimilarityResult,
+    variant::{Variant, WidebandMapping},
     visqol_manager::VisqolManager,
 };
 
 pub mod command_line_utils;
 pub mod output_utils;
 pub mod path_pair;
-pub use crate::command_line_utils::{build_file_pair_paths, CommandLineArgs};
+pub use crate::command_line_utils::{build_file_pair_paths, CommandLineArgs, Comparison};
 use crate::path_pair::PathPair;
 
-fn run<const NUM_BANDS: usize>(
+fn run(
     path_pairs: &Vec<PathPair>,
-    visqol: &mut VisqolManager<NUM_BANDS>,
-) -> Result<Vec<SimilarityResult>, Box<dyn Error>> {
-    let mut results = Vec::<SimilarityResult>::with_capacity(path_pairs.len());
+    args: &CommandLineArgs,
+    visqol: &mut VisqolManager,
+) -> Result<Vec<MultiChannelSimilarityResult>, Box<dyn Error>> {
+    let channel_mode = args.channel_mode();
+    visqol.set_time_ranges(args.reference_range(), args.degraded_range());
+    let mut results = Vec::<MultiChannelSimilarityResult>::with_capacity(path_pairs.len());
     for file_pair in path_pairs {
-        let result = visqol.run(&file_pair.reference, &file_pair.degraded)?;
+        let ref_channels = load(&file_pair.reference, &args.reference_format)?;
+        let deg_channels = load(&file_pair.degraded, &args.degraded_format)?;
+        let result = visqol.score_channels(&ref_channels, &deg_channels, &channel_mode)?;
         results.push(result);
     }
     Ok(results)
 }
+
+/// Compares the degraded file of the single pair in `path_pairs` with `degraded_file_b` against its reference.
+/// All files are downmixed to mono, other channel modes are rejected.
+fn run_ab(
+    path_pairs: &[PathPair],
+    degraded_file_b: &str,
+    args: &CommandLineArgs,
+    visqol: &mut VisqolManager,
+) -> Result<AbComparisonResult, Box<dyn Error>> {
+    let [file_pair] = path_pairs else {
+        return Err(
+            "compare_ab requires --reference_file and --degraded_file instead of a batch!".into(),
+        );
+    };
+    if !matches!(args.channel_mode(), ChannelMode::Downmix) {
+        return Err(
+            "compare_ab downmixes all files to mono and cannot be combined with --per_channel, --mid_side or --channel!"
+                .into(),
+        );
+    }
+    visqol.set_time_ranges(args.reference_range(), args.degraded_range());
+    visqol.score_ab_signals(
+        load_as_mono(&file_pair.reference, &args.reference_format)?,
+        load_as_mono(&file_pair.degraded, &args.degraded_format)?,
+        load_as_mono(degraded_file_b, &args.degraded_format)?,
+    )
+}
+
+/// Loads `file_path` downmixed to mono, as raw PCM if a `raw_format` is given.
+fn load_as_mono(
+    file_path: &str,
+    raw_format: &Option<RawFormat>,
+) -> Result<AudioSignal, Box<dyn Error>> {
+    match raw_format {
+        Some(raw_format) => audio_utils::load_raw_as_mono(file_path, raw_format),
+        None => audio_utils::load_as_mono(file_path),
+    }
+}
+
+/// Loads the channels of `file_path` as raw PCM if a `raw_format` is given, otherwise the format is detected from the file header.
+fn load(
+    file_path: &str,
+    raw_format: &Option<RawFormat>,
+) -> Result<Vec<AudioSignal>, Box<dyn Error>> {
+    match raw_format {
+        Some(raw_format) => audio_utils::load_raw_channels(file_path, raw_format),
+        None => audio_utils::load_channels(file_path),
+    }
+}
+
+/// Returns the wideband variant that uses the lattice model in `model_path`.
+#[cfg(feature = "lattice")]
+fn lattice_variant(model_path: &str) -> Result<Variant, Box<dyn Error>> {
+    Ok(Variant::WidebandLattice {
+        model_path: model_path.to_string(),
+    })
+}
+
+/// The lattice model is not available without the `lattice` feature.
+#[cfg(not(feature = "lattice"))]
+fn lattice_variant(_model_path: &str) -> Result<Variant, Box<dyn Error>> {
+    Err("The lattice model requires the `lattice` feature of visqol!".into())
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
-    // Set up logger
+    // Set up logger. Only messages of visqol and visqol-rs are shown, the model runtime is very chatty.
     TermLogger::init(
         LevelFilter::Trace,
-        Config::default(),
+        ConfigBuilder::new().add_filter_allow_str("visqol").build(),
         TerminalMode::Stdout,
         ColorChoice::Always,
     )?;
@@ -41,32 +114,59 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let files_to_compare = build_file_pair_paths(&args)?;
 
-    let variant: Variant;
-    let mut visqol_speech: VisqolManager<NUM_BANDS_SPEECH>;
-    let mut visqol_audio: VisqolManager<NUM_BANDS_AUDIO>;
-
-    let results: Vec<SimilarityResult>;
-    match &args.subcommand {
+    let variant = match &args.subcommand {
         command_line_utils::Subcommands::Wideband {
             use_unscaled_speech_mos_mapping,
-        } => {
-            variant = Variant::Wideband {
-                use_unscaled_mos_mapping: *use_unscaled_speech_mos_mapping,
-            };
-            visqol_speech = VisqolManager::new(variant, args.search_window_radius);
-            results = run(&files_to_compare, &mut visqol_speech)?;
-        }
+            use_lattice_model,
+            use_svr_model,
+            ..
+        } => match (use_lattice_model, use_svr_model) {
+            (Some(model_path), _) => lattice_variant(model_path)?,
+            (None, Some(model_path)) => Variant::Wideband {
+                mapping: WidebandMapping::Svr {
+                    model_path: model_path.clone(),
+                },
+            },
+            (None, None) => Variant::Wideband {
+                mapping: WidebandMapping::Polynomial {
+                    use_unscaled_mos_mapping: *use_unscaled_speech_mos_mapping,
+                },
+            },
+        },
         command_line_utils::Subcommands::Fullband {
             similarity_to_quality_model,
-        } => {
-            variant = Variant::Fullband {
-                model_path: similarity_to_quality_model.clone(),
-            };
-            visqol_audio = VisqolManager::new(variant, args.search_window_radius);
-            results = run(&files_to_compare, &mut visqol_audio)?;
-        }
+            ..
+        } => Variant::Fullband {
+            model_path: similarity_to_quality_model.clone(),
+        },
+        command_line_utils::Subcommands::Narrowband {
+            use_unscaled_speech_mos_mapping,
+            ..
+        } => Variant::Narrowband {
+            use_unscaled_mos_mapping: *use_unscaled_speech_mos_mapping,
+        },
+        command_line_utils::Subcommands::SuperWideband {
+            use_unscaled_speech_mos_mapping,
+            ..
+        } => Variant::SuperWideband {
+            use_unscaled_mos_mapping: *use_unscaled_speech_mos_mapping,
+        },
+    };
+
+    let mut visqol = VisqolManager::from_config(variant, &args.visqol_config()?)?;
+    if let Some(Comparison::CompareAb { degraded_file_b }) = args.comparison() {
+        let result = run_ab(&files_to_compare, degraded_file_b, &args, &mut visqol)?;
+        output_utils::write_ab_result(
+            &args,
+            &result,
+            &files_to_compare[0],
+            degraded_file_b,
+            visqol.config(),
+        );
+        return Ok(());
     }
+    let results = run(&files_to_compare, &args, &mut visqol)?;
 
-    output_utils::write_results(&args, &results, &files_to_compare);
+    output_utils::write_results(&args, &results, &files_to_compare, visqol.config());
     Ok(())
 }
@@ -1,5 +1,14 @@
 use crate::{command_line_utils::CommandLineArgs, path_pair::PathPair};
-use visqol_rs::similarity_result::SimilarityResult;
+use visqol_rs::{
+    ab_comparison::AbComparisonResult,
+    channel_mode::ChannelMode,
+    impairment_report::Impairment,
+    quality_trace::QualityPoint,
+    similarity_result::{MultiChannelSimilarityResult, SimilarityResult},
+    time_warping::WarpPoint,
+    visqol_config::VisqolConfig,
+    worst_segments::{self, WorstSegment},
+};
 
 use csv::WriterBuilder;
 use prettytable::{
@@ -11,14 +20,15 @@ use serde_json;
 /// Writes debug info to either console or to file.
 pub fn write_results(
     args: &CommandLineArgs,
-    results: &Vec<SimilarityResult>,
+    results: &Vec<MultiChannelSimilarityResult>,
     file_pairs: &Vec<PathPair>,
+    config: &VisqolConfig,
 ) {
     let version_number = env!("CARGO_PKG_VERSION");
     println!("ViSQOL conformance version: {version_number:}");
 
     if let Some(json_output_path) = &args.output_debug {
-        write_debug_json(json_output_path, results);
+        write_debug_json(args, json_output_path, results, config);
     }
 
     if let Some(csv_output_path) = &args.results_csv {
@@ -30,40 +40,186 @@ pub fn write_results(
     }
 }
 
+/// Writes the A/B comparison to console and, if requested, its debug info as JSON.
+pub fn write_ab_result(
+    args: &CommandLineArgs,
+    result: &AbComparisonResult,
+    file_pair: &PathPair,
+    degraded_file_b: &str,
+    config: &VisqolConfig,
+) {
+    let version_number = env!("CARGO_PKG_VERSION");
+    println!("ViSQOL conformance version: {version_number:}");
+
+    if let Some(json_output_path) = &args.output_debug {
+        let mut json_result = serde_json::to_value(result).expect("Could not format JSON!");
+        if let Some(fields) = json_result.as_object_mut() {
+            fields.insert(
+                "config".to_string(),
+                serde_json::to_value(config).expect("Could not format JSON!"),
+            );
+        }
+        std::fs::write(
+            json_output_path,
+            serde_json::to_string_pretty(&json_result).expect("Could not format JSON!"),
+        )
+        .unwrap_or_else(|_| panic!("Could not write JSON to {}!", json_output_path.as_str()));
+    }
+
+    if args.verbose {
+        println!("Reference Filepath:\t {:}", file_pair.reference);
+        println!("Degraded Filepath A:\t {:}", file_pair.degraded);
+        println!("Degraded Filepath B:\t {degraded_file_b:}");
+    }
+    println!("A:");
+    write_channel_to_console(args, &result.result_a);
+    println!("B:");
+    write_channel_to_console(args, &result.result_b);
+    println!("MOS-LQO A - B:\t\t{}", result.moslqo_difference);
+    println!(
+        "{}% CI:\t\t[{}, {}]",
+        result.confidence_level * 100.0,
+        result.lower,
+        result.upper
+    );
+    println!("p-value:\t\t{}", result.p_value);
+}
+
 /// Writes debug info to console
-fn write_to_console(args: &CommandLineArgs, result: &SimilarityResult, file_pair: &PathPair) {
+fn write_to_console(
+    args: &CommandLineArgs,
+    result: &MultiChannelSimilarityResult,
+    file_pair: &PathPair,
+) {
     if args.verbose {
         println!("Reference Filepath:\t {:}", file_pair.reference);
         println!("Degraded Filepath:\t {:}", file_pair.degraded);
     }
 
+    if args.channel_mode() == ChannelMode::Downmix {
+        write_channel_to_console(args, &result.channels[0].result);
+        return;
+    }
+
+    for channel in &result.channels {
+        println!("Channel:\t\t{}", channel.channel);
+        write_channel_to_console(args, &channel.result);
+    }
+    println!("MOS-LQO (lowest):\t{}", result.moslqo);
+    println!("MOS-LQO (mean):\t\t{}", result.mean_moslqo);
+}
+
+/// Writes the debug info of a single channel to console
+fn write_channel_to_console(args: &CommandLineArgs, result: &SimilarityResult) {
     println!("MOS-LQO:\t\t{}", result.moslqo);
+    if let Some(interval) = &result.moslqo_confidence {
+        println!(
+            "MOS-LQO {}% CI:\t[{}, {}] (standard error {})",
+            interval.confidence_level * 100.0,
+            interval.lower,
+            interval.upper,
+            interval.standard_error
+        );
+    }
+
+    if let Some(count) = args.report_worst {
+        write_worst_segments(&worst_segments::find_worst_segments(result, count));
+    }
 
     if args.verbose {
+        write_alignment_info(result);
         write_fvnsim_table(result);
         write_patch_similarity(result);
+        if let Some(warp_path) = &result.warp_path {
+            write_warp_path(warp_path);
+        }
+        if !result.impairments.is_empty() {
+            write_impairments(&result.impairments);
+        }
+        write_quality_trace(&result.quality_trace);
     }
 }
+
+/// Writes the global delay, its confidence and the levels of the signals to console
+fn write_alignment_info(result: &SimilarityResult) {
+    println!("Global delay:\t\t{} s", result.global_delay);
+    match result.alignment_confidence {
+        Some(confidence) => println!("Alignment confidence:\t{confidence}"),
+        None => println!("Alignment confidence:\t-"),
+    }
+    println!("Reference SPL:\t\t{} dB", result.ref_spl_db);
+    println!("Degraded SPL:\t\t{} dB", result.deg_spl_db);
+    match result.clock_drift_ppm {
+        Some(drift) => println!("Clock drift:\t\t{drift} ppm"),
+        None => println!("Clock drift:\t\t-"),
+    }
+}
+
 /// Writes json formatted debug information.
-fn write_debug_json(json_output_path: &String, results: &Vec<SimilarityResult>) {
+/// In downmix mode the result of the mono signal is written, otherwise all channel results and their aggregate.
+/// The effective `config` is added to the result, so that it can be reproduced.
+fn write_debug_json(
+    args: &CommandLineArgs,
+    json_output_path: &String,
+    results: &Vec<MultiChannelSimilarityResult>,
+    config: &VisqolConfig,
+) {
     let mut json_output = String::new();
     for result in results {
-        json_output = serde_json::to_string_pretty(result).expect("Could not format JSON!");
+        let mut json_result = if args.channel_mode() == ChannelMode::Downmix {
+            serde_json::to_value(&result.channels[0].result)
+        } else {
+            serde_json::to_value(result)
+        }
+        .expect("Could not format JSON!");
+        if let Some(count) = args.report_worst {
+            if args.channel_mode() == ChannelMode::Downmix {
+                insert_worst_segments(&mut json_result, &result.channels[0].result, count);
+            } else if let Some(json_channels) = json_result["channels"].as_array_mut() {
+                for (json_channel, channel) in json_channels.iter_mut().zip(&result.channels) {
+                    insert_worst_segments(json_channel, &channel.result, count);
+                }
+            }
+        }
+        if let Some(fields) = json_result.as_object_mut() {
+            fields.insert(
+                "config".to_string(),
+                serde_json::to_value(config).expect("Could not format JSON!"),
+            );
+        }
+        json_output = serde_json::to_string_pretty(&json_result).expect("Could not format JSON!");
     }
     std::fs::write(json_output_path, json_output)
         .unwrap_or_else(|_| panic!("Could not write JSON to {}!", json_output_path.as_str()));
 }
 
-/// Writes computes MOS values to csv file.
-fn write_results_to_csv(csv_output_path: &String, results: &Vec<SimilarityResult>) {
+/// Adds the `count` worst segments of `result` to its JSON object `json_result`.
+fn insert_worst_segments(
+    json_result: &mut serde_json::Value,
+    result: &SimilarityResult,
+    count: usize,
+) {
+    if let Some(fields) = json_result.as_object_mut() {
+        fields.insert(
+            "worst_segments".to_string(),
+            serde_json::to_value(worst_segments::find_worst_segments(result, count))
+                .expect("Could not format JSON!"),
+        );
+    }
+}
+
+/// Writes computes MOS values to csv file. Each compared channel is written to its own row,
+/// starting with the channel label. Rows differ in length with the number of patches.
+fn write_results_to_csv(csv_output_path: &String, results: &[MultiChannelSimilarityResult]) {
     let mut writer = WriterBuilder::new()
         .has_headers(false)
+        .flexible(true)
         .delimiter(b',')
         .from_path(csv_output_path)
         .expect("Failed to instantiate CSV writer!");
-    for result in results {
+    for channel in results.iter().flat_map(|result| &result.channels) {
         writer
-            .serialize(result)
+            .serialize((&channel.channel, &channel.result))
             .expect("Failed to serialize SimilarityResult!");
     }
     writer.flush().expect("Failed to flush csv file!")
@@ -122,6 +278,99 @@ fn write_patch_similarity(result: &SimilarityResult) {
     table.printstd();
 }
 
+/// Formats the warp path of the time warping stage to a table and outputs it to the console
+fn write_warp_path(warp_path: &[WarpPoint]) {
+    let mut table = Table::new();
+    table.set_format(get_default_table_format());
+
+    for point in warp_path {
+        table.add_row(Row::new(vec![
+            Cell::new(&point.ref_time.to_string()[..]),
+            Cell::new(&point.deg_time.to_string()[..]),
+        ]));
+    }
+    table.set_titles(Row::new(vec![Cell::new("Ref Time"), Cell::new("Deg Time")]));
+    table.printstd();
+}
+
+/// Formats the detected dropouts, insertions and repetitions to a table and outputs it to the console
+fn write_impairments(impairments: &[Impairment]) {
+    let mut table = Table::new();
+    table.set_format(get_default_table_format());
+
+    for impairment in impairments {
+        table.add_row(Row::new(vec![
+            Cell::new(&format!("{:?}", impairment.kind)[..]),
+            Cell::new(
+                &format!(
+                    "{} - {}",
+                    impairment.ref_start_time, impairment.ref_end_time
+                )[..],
+            ),
+            Cell::new(
+                &format!(
+                    "{} - {}",
+                    impairment.deg_start_time, impairment.deg_end_time
+                )[..],
+            ),
+        ]));
+    }
+    table.set_titles(Row::new(vec![
+        Cell::new("Impairment"),
+        Cell::new("Ref: Start - End"),
+        Cell::new("Deg: Start - End"),
+    ]));
+    table.printstd();
+}
+
+/// Formats the MOS of the patch groups over time to a table and outputs it to the console
+fn write_quality_trace(quality_trace: &[QualityPoint]) {
+    let mut table = Table::new();
+    table.set_format(get_default_table_format());
+
+    for point in quality_trace {
+        table.add_row(Row::new(vec![
+            Cell::new(&format!("{} - {}", point.ref_start_time, point.ref_end_time)[..]),
+            Cell::new(&point.moslqo.to_string()[..]),
+        ]));
+    }
+    table.set_titles(Row::new(vec![
+        Cell::new("Ref: Start - End"),
+        Cell::new("MOS-LQO"),
+    ]));
+    table.printstd();
+}
+
+/// Formats the worst segments to a table, with the drop in similarity of their worst bands, and outputs it to the console
+fn write_worst_segments(worst_segments: &[WorstSegment]) {
+    let mut table = Table::new();
+    table.set_format(get_default_table_format());
+
+    for (rank, segment) in worst_segments.iter().enumerate() {
+        let worst_bands = segment
+            .worst_bands
+            .iter()
+            .map(|band| format!("{} Hz (-{:.3})", band.center_freq, band.similarity_drop))
+            .collect::<Vec<_>>()
+            .join(", ");
+        table.add_row(Row::new(vec![
+            Cell::new(&(rank + 1).to_string()[..]),
+            Cell::new(&segment.similarity.to_string()[..]),
+            Cell::new(&format!("{} - {}", segment.ref_start_time, segment.ref_end_time)[..]),
+            Cell::new(&format!("{} - {}", segment.deg_start_time, segment.deg_end_time)[..]),
+            Cell::new(&worst_bands[..]),
+        ]));
+    }
+    table.set_titles(Row::new(vec![
+        Cell::new("Rank"),
+        Cell::new("Similarity"),
+        Cell::new("Ref: Start - End"),
+        Cell::new("Deg: Start - End"),
+        Cell::new("Worst Bands"),
+    ]));
+    table.printstd();
+}
+
 /// Returns the default table format for the path similarity table
 fn get_default_table_format() -> TableFormat {
     FormatBuilder::new()